@@ -0,0 +1,161 @@
+use std::{
+    io,
+    panic,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use image::ImageError;
+use tokio::{
+    sync::watch,
+    task,
+};
+
+use crate::{
+    loader::shrink_to_fit,
+    Filter,
+    LoadHandle,
+    LoadSource,
+};
+
+/// Decode an image & scale it down to fit within the latest size received
+/// from `sizes` using `filter_mode` on tokio's blocking thread pool,
+/// returning a handle which is ready to draw with
+/// [`crate::Image::from_handle`]. Images are scaled like with a
+/// [`crate::Loader`].
+///
+/// Send the new size whenever the area the image is drawn in changes. The
+/// image is only decoded once, & is scaled to whichever size is current once
+/// it's decoded. A size sent while the image is being scaled supersedes that
+/// load: the scaled image is discarded & the image is scaled again to the new
+/// size. Closing the sender keeps the last size sent.
+///
+/// Dropping the future cancels the load, but decoding can't be interrupted:
+/// an image which is still being decoded is decoded to completion on the
+/// blocking pool, and only the scaling is skipped.
+///
+/// Requires the `tokio` feature.
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use tokio::sync::watch;
+/// # use tui_image::Filter;
+/// # async fn example() {
+/// let (size, sizes) = watch::channel((1920, 1080));
+/// let load = tokio::spawn(tui_image::load_image(
+///     Path::new("photo.jpg"),
+///     sizes,
+///     Filter::Lanczos3,
+/// ));
+///
+/// // The area changed before the image finished loading.
+/// size.send_replace((1280, 720));
+///
+/// let handle = load.await.unwrap();
+/// # }
+/// ```
+pub async fn load_image(
+    source: impl Into<LoadSource>,
+    mut sizes: watch::Receiver<(u32, u32)>,
+    filter_mode: impl Into<Filter>,
+) -> LoadHandle {
+    let source = source.into();
+    let filter_mode = filter_mode.into();
+    let handle = LoadHandle::pending(&source);
+    let cancelled = Cancelled::default();
+
+    let flag = Arc::clone(&cancelled.0);
+    let result = task::spawn_blocking(move || {
+        let image = source.decode()?;
+        loop {
+            if flag.load(Ordering::Relaxed) {
+                return Err(ImageError::IoError(io::ErrorKind::Interrupted.into()));
+            }
+            let size = *sizes.borrow_and_update();
+            let scaled = shrink_to_fit(&image, size, filter_mode);
+            if !sizes.has_changed().unwrap_or(false) {
+                return Ok(scaled.unwrap_or(image));
+            }
+        }
+    })
+    .await;
+
+    handle.finish(match result {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => panic::resume_unwind(error.into_panic()),
+        Err(error) => Err(ImageError::IoError(io::Error::other(error))),
+    });
+    handle
+}
+
+/// Signals the blocking task to stop once the future awaiting it is dropped.
+#[derive(Default)]
+struct Cancelled(Arc<AtomicBool>);
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{
+        DynamicImage,
+        GenericImageView,
+        ImageOutputFormat,
+        RgbaImage,
+    };
+    use tokio::runtime::Builder;
+
+    use super::*;
+
+    fn load(source: impl Into<LoadSource>, sizes: watch::Receiver<(u32, u32)>) -> LoadHandle {
+        Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(load_image(source, sizes, Filter::Nearest))
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    #[test]
+    fn scales_to_latest_size() {
+        let (size, sizes) = watch::channel((64, 64));
+        size.send_replace((16, 16));
+
+        let handle = load(png(64, 32), sizes);
+        assert_eq!(handle.image().unwrap().dimensions(), (16, 8));
+    }
+
+    #[test]
+    fn keeps_last_size_once_sender_is_closed() {
+        let (size, sizes) = watch::channel((64, 64));
+        size.send_replace((8, 8));
+        drop(size);
+
+        let handle = load(png(64, 32), sizes);
+        assert_eq!(handle.image().unwrap().dimensions(), (8, 4));
+    }
+
+    #[test]
+    fn reports_decode_errors() {
+        let (_size, sizes) = watch::channel((64, 64));
+
+        let handle = load(&b"not an image"[..], sizes);
+        assert!(handle.error().is_some());
+    }
+}
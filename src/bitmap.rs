@@ -0,0 +1,58 @@
+/// A straight alpha rgba image, used for the scaled image while drawing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Bitmap {
+    /// Create a bitmap by calling `pixel` for each position in row-major order.
+    pub(crate) fn from_fn(
+        width: u32,
+        height: u32,
+        mut pixel: impl FnMut(u32, u32) -> [u8; 4],
+    ) -> Self {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| pixel(x, y))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub(crate) fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the pixel at (`x`, `y`).
+    pub(crate) fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// The pixels in row-major order.
+    pub(crate) fn pixels(&self) -> &[[u8; 4]] {
+        &self.pixels
+    }
+
+    pub(crate) fn pixels_mut(&mut self) -> &mut [[u8; 4]] {
+        &mut self.pixels
+    }
+
+    /// The pixels as a flat buffer of rgba bytes.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.pixels.as_flattened()
+    }
+
+    /// Copy the `width` x `height` region starting at (`x`, `y`).
+    pub(crate) fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self::from_fn(width, height, |column, row| self.get(x + column, y + row))
+    }
+}
@@ -0,0 +1,233 @@
+/// The query asking the terminal for the size of a cell in pixels. Terminals
+/// supporting it reply with `CSI 6 ; height ; width t`, which can be parsed
+/// with [`CellSize::from_cell_size_reply`].
+pub const CELL_SIZE_QUERY: &str = "\x1b[16t";
+
+/// The query asking the terminal for the size of its text area in pixels.
+/// Terminals supporting it reply with `CSI 4 ; height ; width t`, which can be
+/// parsed with [`CellSize::from_window_size_reply`].
+pub const WINDOW_SIZE_QUERY: &str = "\x1b[14t";
+
+/// The size of a single terminal cell in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CellSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Default for CellSize {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 20,
+        }
+    }
+}
+
+impl CellSize {
+    /// Get the cell size of the terminal attached to stdout from the kernel.
+    /// Returns `None` if stdout isn't a terminal or the terminal doesn't report
+    /// its size in pixels.
+    #[cfg(unix)]
+    pub fn from_stdout() -> Option<Self> {
+        Self::from_terminal(&std::io::stdout())
+    }
+
+    /// Get the cell size of a terminal from the kernel using `TIOCGWINSZ`.
+    /// Returns `None` if `terminal` isn't a terminal or the terminal doesn't
+    /// report its size in pixels.
+    #[cfg(unix)]
+    pub fn from_terminal(terminal: &impl std::os::unix::io::AsRawFd) -> Option<Self> {
+        // SAFETY: winsize is plain old data, and TIOCGWINSZ only writes to the
+        // struct it's passed.
+        let size = unsafe {
+            let mut size: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(terminal.as_raw_fd(), libc::TIOCGWINSZ, &mut size) != 0 {
+                return None;
+            }
+            size
+        };
+
+        Self::from_window_size(size.ws_xpixel, size.ws_ypixel, size.ws_col, size.ws_row)
+    }
+
+    /// Parse the terminal's reply to [`CELL_SIZE_QUERY`], e.g.
+    /// `"\x1b[6;20;10t"`.
+    pub fn from_cell_size_reply(reply: &[u8]) -> Option<Self> {
+        let (height, width) = parse_size_reply(reply, 6)?;
+        (width > 0 && height > 0).then_some(Self { width, height })
+    }
+
+    /// Parse the terminal's reply to [`WINDOW_SIZE_QUERY`], e.g.
+    /// `"\x1b[4;480;800t"`, dividing it by the size of the terminal in cells.
+    pub fn from_window_size_reply(reply: &[u8], columns: u16, rows: u16) -> Option<Self> {
+        let (height, width) = parse_size_reply(reply, 4)?;
+        Self::from_window_size(width, height, columns, rows)
+    }
+
+    fn from_window_size(width: u16, height: u16, columns: u16, rows: u16) -> Option<Self> {
+        if width == 0 || height == 0 || columns == 0 || rows == 0 {
+            return None;
+        }
+
+        Some(Self {
+            width: (width / columns).max(1),
+            height: (height / rows).max(1),
+        })
+    }
+}
+
+/// Parse a `CSI kind ; height ; width t` reply, ignoring any surrounding
+/// input.
+fn parse_size_reply(reply: &[u8], kind: u16) -> Option<(u16, u16)> {
+    let reply = String::from_utf8_lossy(reply);
+    reply.split("\x1b[").skip(1).find_map(|sequence| {
+        let (parameters, _) = sequence.split_once('t')?;
+        let mut parameters = parameters
+            .split(';')
+            .map(|parameter| parameter.parse().ok());
+
+        match (
+            parameters.next()??,
+            parameters.next()??,
+            parameters.next()??,
+            parameters.next(),
+        ) {
+            (reply_kind, height, width, None) if reply_kind == kind => Some((height, width)),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cell_size_reply() {
+        let size = Some(CellSize {
+            width: 10,
+            height: 20,
+        });
+        assert_eq!(CellSize::from_cell_size_reply(b"\x1b[6;20;10t"), size);
+        assert_eq!(
+            CellSize::from_cell_size_reply(b"abc\x1b[?62;4c\x1b[6;20;10tdef"),
+            size
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_cell_size_replies() {
+        for reply in [
+            &b"\x1b[4;20;10t"[..],
+            b"\x1b[6;0;10t",
+            b"\x1b[6;20;0t",
+            b"\x1b[6;20;10;1t",
+            b"\x1b[6;20t",
+            b"\x1b[6;20;10",
+            b"",
+        ] {
+            assert_eq!(CellSize::from_cell_size_reply(reply), None, "{reply:?}");
+        }
+    }
+
+    #[test]
+    fn parses_window_size_reply() {
+        let size = Some(CellSize {
+            width: 10,
+            height: 20,
+        });
+        assert_eq!(
+            CellSize::from_window_size_reply(b"\x1b[4;480;800t", 80, 24),
+            size
+        );
+        assert_eq!(
+            CellSize::from_window_size_reply(b"x\x1b[6;1;1t\x1b[4;480;800ty", 80, 24),
+            size
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_window_size_replies() {
+        for (reply, columns, rows) in [
+            (&b"\x1b[6;480;800t"[..], 80, 24),
+            (b"\x1b[4;0;800t", 80, 24),
+            (b"\x1b[4;480;0t", 80, 24),
+            (b"\x1b[4;480;800;0t", 80, 24),
+            (b"\x1b[4;480;800t", 0, 24),
+            (b"\x1b[4;480;800t", 80, 0),
+        ] {
+            assert_eq!(
+                CellSize::from_window_size_reply(reply, columns, rows),
+                None,
+                "{reply:?}"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_size_from_pty() {
+        use std::{
+            os::fd::{
+                AsRawFd,
+                FromRawFd,
+                OwnedFd,
+            },
+            ptr,
+        };
+
+        let (mut leader, mut follower) = (0, 0);
+        // SAFETY: openpty only writes the descriptors it's passed, and the
+        // returned descriptors are owned by nothing else.
+        let (leader, follower) = unsafe {
+            assert_eq!(
+                libc::openpty(
+                    &mut leader,
+                    &mut follower,
+                    ptr::null_mut(),
+                    ptr::null(),
+                    ptr::null(),
+                ),
+                0
+            );
+            (OwnedFd::from_raw_fd(leader), OwnedFd::from_raw_fd(follower))
+        };
+
+        let set_size = |size: libc::winsize| {
+            // SAFETY: TIOCSWINSZ only reads the struct it's passed.
+            let result = unsafe { libc::ioctl(leader.as_raw_fd(), libc::TIOCSWINSZ, &size) };
+            assert_eq!(result, 0);
+        };
+
+        set_size(libc::winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 800,
+            ws_ypixel: 480,
+        });
+        assert_eq!(
+            CellSize::from_terminal(&follower),
+            Some(CellSize {
+                width: 10,
+                height: 20,
+            })
+        );
+
+        // Terminals which don't report their size in pixels leave it zeroed.
+        set_size(libc::winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        });
+        assert_eq!(CellSize::from_terminal(&follower), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_non_terminals() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        assert_eq!(CellSize::from_terminal(&file), None);
+    }
+}
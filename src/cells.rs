@@ -0,0 +1,500 @@
+use std::borrow::Cow;
+
+use crate::color::{
+    self,
+    Rgb,
+};
+
+/// The glyphs used to draw an image with [`crate::Protocol::Cells`].
+///
+/// Each cell is drawn with a single glyph covering part of the cell in the
+/// foreground color, with the rest showing the background color. Encodings
+/// using more pixels per cell give higher resolution at the cost of color
+/// accuracy, since each cell can still only show two colors.
+///
+/// [`CellEncoding::Braille`] & [`CellEncoding::Ascii`] are the exceptions,
+/// drawing characters in a single color over the existing background.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum CellEncoding {
+    /// Use the lower half block (`▄`), giving 1x2 pixels per cell.
+    #[default]
+    HalfBlock,
+    /// Use the quadrant block elements (`▘▝▖▗▚▞` etc.), giving 2x2 pixels per
+    /// cell.
+    Quadrant,
+    /// Use the sextant characters from the Symbols for Legacy Computing block
+    /// (`🬀🬁🬂` etc.), giving 2x3 pixels per cell. Requires a font supporting
+    /// Unicode 13.
+    Sextant,
+    /// Use braille patterns (`⠁⠂⠄` etc.), giving 2x4 monochrome dots per
+    /// cell. Each pixel which contrasts with the matte color by luminance is
+    /// drawn as a dot, using the dithering algorithm to pick dots for
+    /// intermediate values.
+    ///
+    /// When `colored` is set, the dots in each cell are drawn with the average
+    /// color of the pixels they cover. Otherwise the colors of the cell are
+    /// left unchanged.
+    Braille { colored: bool },
+    /// Use characters from `ramp`, giving 1x2 pixels per cell. The ramp should
+    /// be ordered from the least to the most ink, and each cell is drawn with
+    /// the character matching how much its pixels contrast with the matte
+    /// color by luminance, e.g. [`CellEncoding::ASCII_RAMP`].
+    ///
+    /// When `colored` is set, each character is drawn with the average color
+    /// of its pixels. Otherwise the colors of the cell are left unchanged.
+    ///
+    /// ```
+    /// # use tui_image::CellEncoding;
+    /// let ramp = String::from(" ░▒▓█");
+    /// let encoding = CellEncoding::Ascii {
+    ///     ramp: ramp.into(),
+    ///     colored: true,
+    /// };
+    /// ```
+    Ascii {
+        ramp: Cow<'static, str>,
+        colored: bool,
+    },
+    /// Use the glyph & colors from a custom set which best match each cell.
+    Glyphs(GlyphSet),
+}
+
+/// A set of glyphs to match against the pixels of each cell, used with
+/// [`CellEncoding::Glyphs`].
+///
+/// Each glyph has a coverage bitmap over a grid of `width` x `height` pixels
+/// per cell, where bit `y * width + x` is set if the glyph covers the pixel at
+/// (`x`, `y`). Cells are drawn with the glyph which lets its foreground &
+/// background colors best represent the pixels it covers & leaves uncovered.
+///
+/// ```
+/// # use tui_image::{CellEncoding, GlyphSet};
+/// // Half blocks in both directions, allowing more accurate colors when
+/// // the terminal renders the upper half block better than the lower.
+/// let glyphs = GlyphSet::new(1, 2).glyph('▀', 0b01).glyph('▄', 0b10);
+/// let encoding = CellEncoding::Glyphs(glyphs);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GlyphSet {
+    width: usize,
+    height: usize,
+    glyphs: Vec<(u64, char)>,
+}
+
+impl GlyphSet {
+    /// Create an empty set of glyphs covering `width` x `height` pixels per
+    /// cell.
+    ///
+    /// # Panics
+    /// Panics if the grid is empty or has more than 64 pixels.
+    pub fn new(width: u8, height: u8) -> Self {
+        let (width, height) = (usize::from(width), usize::from(height));
+        assert!(
+            (1..=64).contains(&(width * height)),
+            "glyph grids must have between 1 and 64 pixels"
+        );
+
+        Self {
+            width,
+            height,
+            glyphs: vec![],
+        }
+    }
+
+    /// Add a glyph to the set. Bits of `coverage` outside of the grid are
+    /// ignored.
+    pub fn glyph(mut self, symbol: char, coverage: u64) -> Self {
+        let len = self.width * self.height;
+        let coverage = if len == 64 {
+            coverage
+        } else {
+            coverage & ((1 << len) - 1)
+        };
+        self.glyphs.push((coverage, symbol));
+        self
+    }
+
+    /// The lower half block, matching [`CellEncoding::HalfBlock`].
+    pub fn half_blocks() -> Self {
+        Self::new(1, 2).glyph('▄', 0b10)
+    }
+
+    /// The quadrant block elements, matching [`CellEncoding::Quadrant`].
+    pub fn quadrants() -> Self {
+        QUADRANTS
+            .iter()
+            .enumerate()
+            .fold(Self::new(2, 2), |set, (mask, symbol)| {
+                set.glyph(*symbol, mask as u64)
+            })
+    }
+
+    /// The sextant characters, matching [`CellEncoding::Sextant`].
+    pub fn sextants() -> Self {
+        (0..64).fold(Self::new(2, 3), |set, mask| {
+            set.glyph(sextant(mask), u64::from(mask))
+        })
+    }
+
+    /// The braille patterns, treating each dot as covering its pixel.
+    pub fn braille() -> Self {
+        (0..=u8::MAX).fold(Self::new(2, 4), |set, mask| {
+            let pattern = BRAILLE_DOTS
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| mask >> index & 1 == 1)
+                .fold(0, |pattern, (_, dot)| pattern | u32::from(*dot));
+            set.glyph(
+                char::from_u32(0x2800 + pattern).unwrap_or(' '),
+                u64::from(mask),
+            )
+        })
+    }
+}
+
+/// The glyph & colors chosen to draw a single cell. Colors which are `None`
+/// are left unchanged.
+pub(crate) struct EncodedCell {
+    pub(crate) symbol: char,
+    pub(crate) fg: Option<Rgb>,
+    pub(crate) bg: Option<Rgb>,
+}
+
+/// The bit of a braille pattern for each pixel of a cell in row-major order.
+const BRAILLE_DOTS: [u8; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+/// The quadrant glyph for each mask of foreground pixels, where bits 0-3 are
+/// the top left, top right, bottom left & bottom right pixels.
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Get the sextant glyph for a mask of foreground pixels, where bits 0-5 are
+/// the pixels in row-major order.
+fn sextant(mask: u8) -> char {
+    match mask {
+        0 => ' ',
+        0b010101 => '▌',
+        0b101010 => '▐',
+        0b111111 => '█',
+        _ => {
+            // The sextant block is ordered by mask, skipping the masks which
+            // already have a glyph in the block elements.
+            let offset = mask - 1 - u8::from(mask > 0b010101) - u8::from(mask > 0b101010);
+            char::from_u32(0x1fb00 + u32::from(offset)).unwrap_or(' ')
+        }
+    }
+}
+
+impl CellEncoding {
+    /// A ramp of plain ascii characters for use with [`CellEncoding::Ascii`].
+    pub const ASCII_RAMP: Cow<'static, str> = Cow::Borrowed(" .:-=+*#%@");
+
+    /// The number of (columns, rows) of pixels drawn by a single cell.
+    pub(crate) fn pixels_per_cell(&self) -> (usize, usize) {
+        match self {
+            CellEncoding::HalfBlock => (1, 2),
+            CellEncoding::Quadrant => (2, 2),
+            CellEncoding::Sextant => (2, 3),
+            CellEncoding::Braille { .. } => (2, 4),
+            CellEncoding::Ascii { .. } => (1, 2),
+            CellEncoding::Glyphs(glyphs) => (glyphs.width, glyphs.height),
+        }
+    }
+
+    /// Whether the encoding only distinguishes between pixels which are on or
+    /// off, rather than their colors.
+    pub(crate) fn monochrome(&self) -> bool {
+        matches!(self, CellEncoding::Braille { .. })
+    }
+
+    /// Choose how to draw a cell from its pixels in row-major order.
+    ///
+    /// `pixels` have been reduced to the colors available for the image, or to
+    /// black & white for monochrome encodings. `source` holds the same pixels
+    /// before any reduction, and `background` is the color the image is drawn
+    /// against.
+    pub(crate) fn encode(&self, pixels: &[Rgb], source: &[Rgb], background: Rgb) -> EncodedCell {
+        match self {
+            CellEncoding::HalfBlock => best_partition(pixels, [(0b10, '▄')]),
+            CellEncoding::Quadrant => best_partition(
+                pixels,
+                (0..QUADRANTS.len()).map(|mask| (mask as u64, QUADRANTS[mask])),
+            ),
+            CellEncoding::Sextant => {
+                best_partition(pixels, (0..64).map(|mask| (u64::from(mask), sextant(mask))))
+            }
+            CellEncoding::Glyphs(glyphs) => best_partition(pixels, glyphs.glyphs.iter().copied()),
+            CellEncoding::Braille { colored } => {
+                // Dots are drawn for light pixels on dark backgrounds, and for
+                // dark pixels on light backgrounds.
+                let light_background = color::luminance(background) >= 128;
+
+                let mut pattern = 0;
+                let mut sum = [0u32; 4];
+                for (index, (pixel, source)) in pixels.iter().zip(source).enumerate() {
+                    if (color::luminance(*pixel) >= 128) != light_background {
+                        pattern |= BRAILLE_DOTS[index];
+                        for channel in 0..3 {
+                            sum[channel] += u32::from(source[channel]);
+                        }
+                        sum[3] += 1;
+                    }
+                }
+
+                if pattern == 0 {
+                    return EncodedCell {
+                        symbol: ' ',
+                        fg: None,
+                        bg: None,
+                    };
+                }
+
+                EncodedCell {
+                    symbol: char::from_u32(0x2800 + u32::from(pattern)).unwrap_or(' '),
+                    fg: colored.then(|| {
+                        [0, 1, 2].map(|channel| ((sum[channel] + sum[3] / 2) / sum[3]) as u8)
+                    }),
+                    bg: None,
+                }
+            }
+            CellEncoding::Ascii { ramp, colored } => {
+                let mut sum = [0u32; 3];
+                for pixel in source {
+                    for channel in 0..3 {
+                        sum[channel] += u32::from(pixel[channel]);
+                    }
+                }
+                let count = source.len().max(1) as u32;
+                let average = sum.map(|sum| ((sum + count / 2) / count) as u8);
+
+                let mut ink = usize::from(color::luminance(average));
+                if color::luminance(background) >= 128 {
+                    ink = 255 - ink;
+                }
+
+                let levels = ramp.chars().count();
+                let symbol = ramp
+                    .chars()
+                    .nth((ink * levels.saturating_sub(1) + 127) / 255)
+                    .unwrap_or(' ');
+
+                EncodedCell {
+                    symbol,
+                    fg: colored.then_some(average),
+                    bg: None,
+                }
+            }
+        }
+    }
+}
+
+/// Pick the glyph whose mask splits `pixels` into the two groups best
+/// represented by their average colors. Set bits of each mask select the
+/// pixels drawn in the foreground color.
+pub(crate) fn best_partition(
+    pixels: &[Rgb],
+    glyphs: impl IntoIterator<Item = (u64, char)>,
+) -> EncodedCell {
+    let mut best = None;
+    for (mask, symbol) in glyphs {
+        let mut sums = [[0u32; 4]; 2];
+        for (index, pixel) in pixels.iter().enumerate() {
+            let sum = &mut sums[usize::from(mask >> index & 1 == 1)];
+            for channel in 0..3 {
+                sum[channel] += u32::from(pixel[channel]);
+            }
+            sum[3] += 1;
+        }
+
+        let [bg, fg] = sums.map(|sum| {
+            [0, 1, 2]
+                .map(|channel| (sum[channel] + sum[3] / 2).checked_div(sum[3]).unwrap_or(0) as u8)
+        });
+
+        let error = pixels
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| {
+                let mean = if mask >> index & 1 == 1 { fg } else { bg };
+                (0..3)
+                    .map(|channel| {
+                        let delta = u32::from(pixel[channel].abs_diff(mean[channel]));
+                        delta * delta
+                    })
+                    .sum::<u32>()
+            })
+            .sum::<u32>();
+
+        if best.as_ref().map_or(true, |(best, _)| error < *best) {
+            // Keep empty groups from introducing a color not in the cell.
+            let (fg, bg) = match (sums[1][3], sums[0][3]) {
+                (0, _) => (bg, bg),
+                (_, 0) => (fg, fg),
+                _ => (fg, bg),
+            };
+            best = Some((
+                error,
+                EncodedCell {
+                    symbol,
+                    fg: Some(fg),
+                    bg: Some(bg),
+                },
+            ));
+        }
+    }
+
+    best.map(|(_, cell)| cell).unwrap_or(EncodedCell {
+        symbol: ' ',
+        fg: None,
+        bg: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgb = [0, 0, 0];
+    const WHITE: Rgb = [255, 255, 255];
+
+    #[test]
+    fn encodes_quadrant_checkerboard() {
+        let pixels = [WHITE, BLACK, BLACK, WHITE];
+        let cell = CellEncoding::Quadrant.encode(&pixels, &pixels, BLACK);
+        assert_eq!(
+            (cell.symbol, cell.fg, cell.bg),
+            ('▞', Some(BLACK), Some(WHITE))
+        );
+    }
+
+    #[test]
+    fn encodes_quadrants_by_mask() {
+        for (mask, symbol) in QUADRANTS.iter().enumerate().skip(1).take(14) {
+            let pixels = [0, 1, 2, 3].map(|bit| if mask >> bit & 1 == 1 { WHITE } else { BLACK });
+            let cell = CellEncoding::Quadrant.encode(&pixels, &pixels, BLACK);
+
+            // Each mask is equally well drawn by its complement with the
+            // colors swapped.
+            let complement = QUADRANTS[15 - mask];
+            assert!(
+                (cell.symbol, cell.fg) == (*symbol, Some(WHITE))
+                    || (cell.symbol, cell.fg) == (complement, Some(BLACK)),
+                "{mask}: {}",
+                cell.symbol
+            );
+        }
+    }
+
+    #[test]
+    fn averages_colors_of_each_partition() {
+        let pixels = [[200, 0, 0], [0, 0, 200], [100, 0, 0], [0, 0, 100]];
+        let cell = best_partition(&pixels, [(0b0101, '▌')]);
+        assert_eq!(
+            (cell.symbol, cell.fg, cell.bg),
+            ('▌', Some([150, 0, 0]), Some([0, 0, 150]))
+        );
+    }
+
+    #[test]
+    fn maps_masks_to_sextants() {
+        let cases = [
+            (0, ' '),
+            (1, '\u{1fb00}'),
+            (20, '\u{1fb13}'),
+            (21, '▌'),
+            (22, '\u{1fb14}'),
+            (41, '\u{1fb27}'),
+            (42, '▐'),
+            (43, '\u{1fb28}'),
+            (62, '\u{1fb3b}'),
+            (63, '█'),
+        ];
+        for (mask, symbol) in cases {
+            assert_eq!(sextant(mask), symbol, "{mask}");
+        }
+    }
+
+    #[test]
+    fn uses_every_sextant_once() {
+        let mut symbols = (0..64).map(sextant).collect::<Vec<_>>();
+        symbols.sort_unstable();
+        symbols.dedup();
+        assert_eq!(symbols.len(), 64);
+    }
+
+    #[test]
+    fn picks_ascii_characters_by_contrast() {
+        let ramp = CellEncoding::Ascii {
+            ramp: String::from("ab").into(),
+            colored: false,
+        };
+        let dark = ramp.encode(&[BLACK; 2], &[BLACK; 2], BLACK);
+        assert_eq!((dark.symbol, dark.fg), ('a', None));
+        let light = ramp.encode(&[WHITE; 2], &[WHITE; 2], BLACK);
+        assert_eq!((light.symbol, light.fg), ('b', None));
+
+        // Light backgrounds invert the ramp.
+        let dark = ramp.encode(&[BLACK; 2], &[BLACK; 2], WHITE);
+        assert_eq!(dark.symbol, 'b');
+    }
+
+    #[test]
+    fn matches_builtin_encodings_with_glyph_sets() {
+        let colors = [BLACK, WHITE, [200, 30, 30], [30, 30, 200]];
+        let pairs = [
+            (GlyphSet::half_blocks(), CellEncoding::HalfBlock),
+            (GlyphSet::quadrants(), CellEncoding::Quadrant),
+            (GlyphSet::sextants(), CellEncoding::Sextant),
+        ];
+        for (glyphs, builtin) in pairs {
+            let (width, height) = builtin.pixels_per_cell();
+            let glyphs = CellEncoding::Glyphs(glyphs);
+            // Every combination of the first two colors, and a few cells
+            // using all four.
+            let cells = (0..1 << (width * height))
+                .map(|mask: usize| {
+                    (0..width * height)
+                        .map(|bit| colors[mask >> bit & 1])
+                        .collect::<Vec<_>>()
+                })
+                .chain((0..4).map(|offset| {
+                    (0..width * height)
+                        .map(|index| colors[(index + offset) % 4])
+                        .collect()
+                }));
+
+            for pixels in cells {
+                let expected = builtin.encode(&pixels, &pixels, BLACK);
+                let cell = glyphs.encode(&pixels, &pixels, BLACK);
+                assert_eq!(
+                    (cell.symbol, cell.fg, cell.bg),
+                    (expected.symbol, expected.fg, expected.bg),
+                    "{builtin:?} {pixels:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn masks_coverage_to_grid() {
+        let glyphs = GlyphSet::new(1, 2).glyph('x', u64::MAX);
+        assert_eq!(glyphs.glyphs, [(0b11, 'x')]);
+
+        let glyphs = GlyphSet::new(8, 8).glyph('x', u64::MAX);
+        assert_eq!(glyphs.glyphs, [(u64::MAX, 'x')]);
+    }
+
+    #[test]
+    fn covers_braille_dots() {
+        let glyphs = GlyphSet::braille();
+        assert_eq!(glyphs.glyphs.len(), 256);
+        assert_eq!(glyphs.glyphs[0], (0, '⠀'));
+        // The top left, then the top right dot.
+        assert_eq!(glyphs.glyphs[0b01], (0b01, '⠁'));
+        assert_eq!(glyphs.glyphs[0b10], (0b10, '⠈'));
+        // The bottom row.
+        assert_eq!(glyphs.glyphs[0b1100_0000], (0b1100_0000, '⣀'));
+        assert_eq!(glyphs.glyphs[0xff], (0xff, '⣿'));
+    }
+}
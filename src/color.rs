@@ -0,0 +1,297 @@
+use tui::style::Color;
+
+/// An 8-bit per channel rgb triple.
+pub(crate) type Rgb = [u8; 3];
+
+/// The default xterm values for the 16 standard ansi colors.
+const ANSI: [Rgb; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xcd, 0x00, 0x00],
+    [0x00, 0xcd, 0x00],
+    [0xcd, 0xcd, 0x00],
+    [0x00, 0x00, 0xee],
+    [0xcd, 0x00, 0xcd],
+    [0x00, 0xcd, 0xcd],
+    [0xe5, 0xe5, 0xe5],
+    [0x7f, 0x7f, 0x7f],
+    [0xff, 0x00, 0x00],
+    [0x00, 0xff, 0x00],
+    [0xff, 0xff, 0x00],
+    [0x5c, 0x5c, 0xff],
+    [0xff, 0x00, 0xff],
+    [0x00, 0xff, 0xff],
+    [0xff, 0xff, 0xff],
+];
+
+/// The named colors matching each entry of [`ANSI`].
+const NAMED: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// The channel levels used by the 6x6x6 color cube of the 256 color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The range of colors the image will be reduced to before being displayed.
+///
+/// Terminals without truecolor support will approximate or ignore rgb colors,
+/// so lower depths map each pixel to the perceptually nearest color the
+/// terminal is able to show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorDepth {
+    /// Use 24-bit rgb colors.
+    #[default]
+    TrueColor,
+    /// Use the color cube & grayscale ramp of the xterm 256 color palette.
+    /// The first 16 entries are skipped since they depend on the terminal's
+    /// theme.
+    Indexed256,
+    /// Use the 16 standard named colors.
+    Ansi16,
+    /// Use only black & white.
+    Monochrome,
+}
+
+impl ColorDepth {
+    /// Find the nearest color available at this depth, returning both the
+    /// color to display and its rgb value.
+    pub(crate) fn quantize(self, rgb: Rgb) -> (Color, Rgb) {
+        match self {
+            ColorDepth::TrueColor => (Color::Rgb(rgb[0], rgb[1], rgb[2]), rgb),
+            ColorDepth::Indexed256 => {
+                let cube = rgb.map(|channel| {
+                    CUBE_LEVELS
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, level)| level.abs_diff(channel))
+                        .map(|(index, _)| index as u8)
+                        .unwrap_or_default()
+                });
+                let cube = 16 + cube[0] * 36 + cube[1] * 6 + cube[2];
+
+                let gray = (u16::from(rgb[0]) + u16::from(rgb[1]) + u16::from(rgb[2])) / 3;
+                let gray = 232 + (gray.saturating_sub(3) / 10).min(23) as u8;
+
+                let index = [cube, gray]
+                    .into_iter()
+                    .min_by_key(|index| distance(rgb, indexed_to_rgb(*index)))
+                    .unwrap_or(cube);
+                (Color::Indexed(index), indexed_to_rgb(index))
+            }
+            ColorDepth::Ansi16 => {
+                let index = (0..ANSI.len())
+                    .min_by_key(|index| distance(rgb, ANSI[*index]))
+                    .unwrap_or_default();
+                (NAMED[index], ANSI[index])
+            }
+            ColorDepth::Monochrome => {
+                if luminance(rgb) >= 128 {
+                    (Color::White, ANSI[15])
+                } else {
+                    (Color::Black, ANSI[0])
+                }
+            }
+        }
+    }
+}
+
+/// The perceptual distance between two colors, using the "redmean"
+/// approximation which weights channels based on the average amount of red.
+pub(crate) fn distance(a: Rgb, b: Rgb) -> u32 {
+    let red_mean = (u32::from(a[0]) + u32::from(b[0])) / 2;
+    let [dr, dg, db] = [0, 1, 2].map(|channel| {
+        let delta = u32::from(a[channel].abs_diff(b[channel]));
+        delta * delta
+    });
+
+    (((512 + red_mean) * dr) >> 8) + 4 * dg + (((767 - red_mean) * db) >> 8)
+}
+
+/// The relative luminance of a color, using the Rec. 709 coefficients.
+pub(crate) fn luminance(rgb: Rgb) -> u8 {
+    let luma = 2126 * u32::from(rgb[0]) + 7152 * u32::from(rgb[1]) + 722 * u32::from(rgb[2]);
+    ((luma + 5000) / 10000) as u8
+}
+
+/// Look up the rgb value of an entry in the xterm 256 color palette.
+pub(crate) fn indexed_to_rgb(index: u8) -> Rgb {
+    match index {
+        0..=15 => ANSI[usize::from(index)],
+        16..=231 => {
+            let index = index - 16;
+            [
+                CUBE_LEVELS[usize::from(index / 36)],
+                CUBE_LEVELS[usize::from(index / 6 % 6)],
+                CUBE_LEVELS[usize::from(index % 6)],
+            ]
+        }
+        232..=255 => {
+            let level = 8 + (index - 232) * 10;
+            [level, level, level]
+        }
+    }
+}
+
+/// Convert a tui color to rgb.
+///
+/// Named and indexed colors use the default xterm palette. Returns `None` for
+/// [`Color::Reset`] since the actual color is up to the terminal.
+pub(crate) fn to_rgb(color: Color) -> Option<Rgb> {
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some([r, g, b]),
+        Color::Indexed(index) => index,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+    };
+
+    Some(indexed_to_rgb(index))
+}
+
+/// Composite a straight alpha rgba pixel over an opaque background.
+pub(crate) fn blend(pixel: [u8; 4], background: Rgb) -> Rgb {
+    let alpha = u16::from(pixel[3]);
+    let mut out = [0; 3];
+    for (out, (fg, bg)) in out.iter_mut().zip(pixel.iter().zip(background)) {
+        let mixed = u16::from(*fg) * alpha + u16::from(bg) * (255 - alpha);
+        *out = ((mixed + 127) / 255) as u8;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends_by_alpha() {
+        let background = [0, 0, 255];
+        assert_eq!(blend([255, 0, 0, 0], background), [0, 0, 255]);
+        assert_eq!(blend([255, 0, 0, 255], background), [255, 0, 0]);
+        assert_eq!(blend([255, 0, 0, 128], background), [128, 0, 127]);
+        assert_eq!(blend([255, 255, 255, 64], [0, 0, 0]), [64, 64, 64]);
+    }
+
+    #[test]
+    fn quantizes_to_color_cube_and_gray_ramp() {
+        let cases = [
+            ([255, 0, 0], Color::Indexed(196), [255, 0, 0]),
+            ([100, 140, 210], Color::Indexed(68), [95, 135, 215]),
+            ([128, 128, 128], Color::Indexed(244), [128, 128, 128]),
+            ([10, 10, 10], Color::Indexed(232), [8, 8, 8]),
+            ([0, 0, 0], Color::Indexed(16), [0, 0, 0]),
+            ([255, 255, 255], Color::Indexed(231), [255, 255, 255]),
+        ];
+        for (rgb, color, actual) in cases {
+            assert_eq!(
+                ColorDepth::Indexed256.quantize(rgb),
+                (color, actual),
+                "{rgb:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn quantizes_to_ansi_colors() {
+        let cases = [
+            ([250, 10, 10], Color::LightRed),
+            ([190, 10, 10], Color::Red),
+            ([120, 120, 120], Color::DarkGray),
+            ([10, 10, 10], Color::Black),
+            ([250, 250, 250], Color::White),
+            ([230, 230, 230], Color::Gray),
+        ];
+        for (rgb, color) in cases {
+            assert_eq!(ColorDepth::Ansi16.quantize(rgb).0, color, "{rgb:?}");
+        }
+    }
+
+    #[test]
+    fn quantizes_to_monochrome_by_luminance() {
+        let cases = [
+            ([200, 200, 200], Color::White),
+            ([50, 50, 50], Color::Black),
+            ([0, 255, 0], Color::White),
+            ([0, 0, 255], Color::Black),
+        ];
+        for (rgb, color) in cases {
+            assert_eq!(ColorDepth::Monochrome.quantize(rgb).0, color, "{rgb:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_truecolor() {
+        assert_eq!(
+            ColorDepth::TrueColor.quantize([1, 2, 3]),
+            (Color::Rgb(1, 2, 3), [1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn round_trips_palette_colors() {
+        for index in 16..=255 {
+            let rgb = indexed_to_rgb(index);
+            assert_eq!(
+                ColorDepth::Indexed256.quantize(rgb),
+                (Color::Indexed(index), rgb),
+                "{index}"
+            );
+        }
+        for (named, rgb) in NAMED.into_iter().zip(ANSI) {
+            assert_eq!(ColorDepth::Ansi16.quantize(rgb), (named, rgb));
+            assert_eq!(to_rgb(named), Some(rgb));
+        }
+    }
+
+    #[test]
+    fn converts_colors_to_rgb() {
+        assert_eq!(to_rgb(Color::Reset), None);
+        assert_eq!(to_rgb(Color::Rgb(1, 2, 3)), Some([1, 2, 3]));
+        assert_eq!(to_rgb(Color::Red), Some([0xcd, 0, 0]));
+        assert_eq!(to_rgb(Color::Indexed(9)), Some([0xff, 0, 0]));
+        assert_eq!(to_rgb(Color::Indexed(67)), Some([95, 135, 175]));
+        assert_eq!(to_rgb(Color::Indexed(255)), Some([238, 238, 238]));
+    }
+
+    #[test]
+    fn weights_distance_perceptually() {
+        assert_eq!(distance([10, 20, 30], [10, 20, 30]), 0);
+        assert_eq!(
+            distance([10, 20, 30], [40, 50, 60]),
+            distance([40, 50, 60], [10, 20, 30])
+        );
+        assert!(distance([0, 0, 0], [0, 10, 0]) > distance([0, 0, 0], [10, 0, 0]));
+        // Blue differences matter more in dark reds, and red differences
+        // more in bright reds.
+        assert!(distance([0, 0, 0], [0, 0, 10]) > distance([0, 0, 0], [10, 0, 0]));
+        assert!(distance([255, 0, 0], [245, 0, 0]) > distance([255, 0, 0], [255, 0, 10]));
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    collections::HashMap,
+    env,
+};
+
+use crate::{
+    CellSize,
+    ColorDepth,
+    Multiplexer,
+    Protocol,
+};
+
+/// The query for the terminal's primary device attributes. Terminals
+/// supporting sixel graphics include attribute `4` in their response.
+pub const DA1_QUERY: &str = "\x1b[c";
+
+/// Build an XTGETTCAP query asking the terminal for the value of each of the
+/// given terminfo capabilities, e.g. `RGB` or `colors`.
+pub fn xtgettcap_query(names: &[&str]) -> String {
+    let names = names
+        .iter()
+        .map(|name| hex_encode(name))
+        .collect::<Vec<_>>();
+    format!("\x1bP+q{}\x1b\\", names.join(";"))
+}
+
+/// The inputs used to detect which graphics a terminal supports.
+///
+/// [`TerminalInfo::from_env`] fills this in from the environment of the
+/// current process. The fields can also be set directly, e.g. to detect
+/// support for a remote terminal or in tests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminalInfo {
+    /// The value of `TERM`.
+    pub term: Option<String>,
+    /// The value of `TERM_PROGRAM`.
+    pub term_program: Option<String>,
+    /// The value of `COLORTERM`.
+    pub colorterm: Option<String>,
+    /// The value of `KITTY_WINDOW_ID`.
+    pub kitty_window_id: Option<String>,
+    /// Whether `TMUX` is set.
+    pub tmux: bool,
+    /// Whether `STY` is set, indicating the process is running under screen.
+    pub screen: bool,
+    /// The attributes reported by the terminal in response to
+    /// [`DA1_QUERY`].
+    pub device_attributes: Vec<u16>,
+    /// The capabilities reported by the terminal in response to an
+    /// [`xtgettcap_query`]. Capabilities without a value map to an empty
+    /// string.
+    pub capabilities: HashMap<String, String>,
+    /// The size of a cell in pixels, if known.
+    pub cell_size: Option<CellSize>,
+}
+
+/// The graphics support detected for a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities {
+    /// The best protocol for drawing images.
+    pub protocol: Protocol,
+    /// The range of colors the terminal can display.
+    pub color_depth: ColorDepth,
+    /// The multiplexer pixel protocol output has to be forwarded through.
+    pub multiplexer: Multiplexer,
+    /// The size of a cell in pixels, if known.
+    pub cell_size: Option<CellSize>,
+}
+
+impl TerminalInfo {
+    /// Read the detection inputs from the environment of the current process,
+    /// along with the cell size the kernel reports for stdout. No terminal
+    /// queries are performed.
+    pub fn from_env() -> Self {
+        let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            term: var("TERM"),
+            term_program: var("TERM_PROGRAM"),
+            colorterm: var("COLORTERM"),
+            kitty_window_id: var("KITTY_WINDOW_ID"),
+            tmux: var("TMUX").is_some(),
+            screen: var("STY").is_some(),
+            #[cfg(unix)]
+            cell_size: CellSize::from_stdout(),
+            ..Self::default()
+        }
+    }
+
+    /// Parse the terminal's response to [`DA1_QUERY`], e.g.
+    /// `"\x1b[?62;4;22c"`. Unrecognized input is ignored.
+    pub fn with_device_attributes(mut self, response: &[u8]) -> Self {
+        let response = String::from_utf8_lossy(response);
+        if let Some(attributes) = response
+            .split("\x1b[?")
+            .nth(1)
+            .and_then(|response| response.split('c').next())
+        {
+            self.device_attributes = attributes
+                .split(';')
+                .filter_map(|attribute| attribute.parse().ok())
+                .collect();
+        }
+        self
+    }
+
+    /// Parse the terminal's responses to an [`xtgettcap_query`], e.g.
+    /// `"\x1bP1+r524742\x1b\\"`. Unrecognized or failed responses are ignored.
+    pub fn with_xtgettcap(mut self, response: &[u8]) -> Self {
+        let response = String::from_utf8_lossy(response);
+        for reply in response.split("\x1bP1+r").skip(1) {
+            let reply = reply.split("\x1b\\").next().unwrap_or_default();
+            for capability in reply.split(';') {
+                let (name, value) = capability.split_once('=').unwrap_or((capability, ""));
+                if let (Some(name), Some(value)) = (hex_decode(name), hex_decode(value)) {
+                    self.capabilities.insert(name, value);
+                }
+            }
+        }
+        self
+    }
+
+    /// Detect the terminal multiplexer the process is running under.
+    pub fn multiplexer(&self) -> Multiplexer {
+        let term = self.term.as_deref().unwrap_or_default();
+
+        if self.tmux || self.term_program.as_deref() == Some("tmux") || term.starts_with("tmux") {
+            Multiplexer::Tmux
+        } else if self.screen || term.starts_with("screen") {
+            Multiplexer::Screen
+        } else {
+            Multiplexer::None
+        }
+    }
+
+    /// Detect the graphics supported by the terminal.
+    ///
+    /// When running under a multiplexer, the outer terminal is detected from
+    /// whatever environment & query responses are forwarded through it.
+    pub fn detect(&self) -> Capabilities {
+        Capabilities {
+            protocol: self.detect_protocol(),
+            color_depth: self.detect_color_depth(),
+            multiplexer: self.multiplexer(),
+            cell_size: self.cell_size,
+        }
+    }
+
+    fn detect_protocol(&self) -> Protocol {
+        let term = self.term.as_deref().unwrap_or_default();
+        let term_program = self.term_program.as_deref().unwrap_or_default();
+
+        if self.kitty_window_id.is_some() || term == "xterm-kitty" || term_program == "ghostty" {
+            Protocol::Kitty
+        } else if matches!(term_program, "iTerm.app" | "WezTerm" | "mintty") {
+            Protocol::Iterm2
+        } else if self.device_attributes.contains(&4)
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+        {
+            Protocol::Sixel
+        } else {
+            Protocol::Cells
+        }
+    }
+
+    fn detect_color_depth(&self) -> ColorDepth {
+        let term = self.term.as_deref().unwrap_or_default();
+        let colors = self
+            .capabilities
+            .get("colors")
+            .and_then(|colors| colors.parse::<u32>().ok());
+
+        // Terminals supporting the kitty & iTerm2 protocols all support
+        // truecolor, but only count as evidence when the terminal hasn't
+        // reported its colors itself.
+        if matches!(self.colorterm.as_deref(), Some("truecolor" | "24bit"))
+            || self.capabilities.contains_key("RGB")
+            || self.capabilities.contains_key("Tc")
+            || (colors.is_none()
+                && matches!(self.detect_protocol(), Protocol::Kitty | Protocol::Iterm2))
+        {
+            ColorDepth::TrueColor
+        } else if colors.map_or(term.contains("256color"), |colors| colors >= 256) {
+            ColorDepth::Indexed256
+        } else if colors.map_or(term == "dumb", |colors| colors < 8) {
+            ColorDepth::Monochrome
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+fn hex_encode(value: &str) -> String {
+    value.bytes().map(|byte| format!("{byte:02X}")).collect()
+}
+
+fn hex_decode(value: &str) -> Option<String> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(term: &str, term_program: &str, colorterm: &str) -> TerminalInfo {
+        let var = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        TerminalInfo {
+            term: var(term),
+            term_program: var(term_program),
+            colorterm: var(colorterm),
+            ..TerminalInfo::default()
+        }
+    }
+
+    #[test]
+    fn builds_xtgettcap_query() {
+        assert_eq!(
+            xtgettcap_query(&["RGB", "colors"]),
+            "\x1bP+q524742;636F6C6F7273\x1b\\"
+        );
+    }
+
+    #[test]
+    fn parses_device_attributes() {
+        let cases: [(&[u8], &[u16]); 4] = [
+            (b"\x1b[?62;4;22c", &[62, 4, 22]),
+            (b"noise\x1b[?1;2cmore", &[1, 2]),
+            (b"\x1b[?c", &[]),
+            (b"garbage", &[]),
+        ];
+        for (response, attributes) in cases {
+            let info = TerminalInfo::default().with_device_attributes(response);
+            assert_eq!(info.device_attributes, attributes, "{response:?}");
+        }
+    }
+
+    #[test]
+    fn parses_xtgettcap_replies() {
+        let info = TerminalInfo::default().with_xtgettcap(
+            b"\x1bP1+r524742\x1b\\\x1bP0+r5463\x1b\\\x1bP1+r636F6C6F7273=323536\x1b\\",
+        );
+        assert_eq!(
+            info.capabilities,
+            HashMap::from([
+                ("RGB".to_string(), String::new()),
+                ("colors".to_string(), "256".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn detects_multiplexer() {
+        let cases = [
+            (env("xterm-256color", "", ""), Multiplexer::None),
+            (env("tmux-256color", "", ""), Multiplexer::Tmux),
+            (env("xterm", "tmux", ""), Multiplexer::Tmux),
+            (env("screen.xterm-256color", "", ""), Multiplexer::Screen),
+            (
+                TerminalInfo {
+                    screen: true,
+                    ..env("xterm", "", "")
+                },
+                Multiplexer::Screen,
+            ),
+        ];
+        for (info, multiplexer) in cases {
+            assert_eq!(info.multiplexer(), multiplexer, "{info:?}");
+        }
+    }
+
+    #[test]
+    fn detects_protocol() {
+        let cases = [
+            (env("xterm-kitty", "", ""), Protocol::Kitty),
+            (env("xterm-ghostty", "ghostty", ""), Protocol::Kitty),
+            (env("xterm-256color", "iTerm.app", ""), Protocol::Iterm2),
+            (env("xterm-256color", "WezTerm", ""), Protocol::Iterm2),
+            (env("foot", "", ""), Protocol::Sixel),
+            (
+                env("xterm", "", "").with_device_attributes(b"\x1b[?62;4;22c"),
+                Protocol::Sixel,
+            ),
+            (env("xterm-256color", "", ""), Protocol::Cells),
+        ];
+        for (info, protocol) in cases {
+            assert_eq!(info.detect().protocol, protocol, "{info:?}");
+        }
+    }
+
+    #[test]
+    fn detects_color_depth() {
+        let cases = [
+            (env("xterm", "", "truecolor"), ColorDepth::TrueColor),
+            (env("xterm", "", "24bit"), ColorDepth::TrueColor),
+            (
+                env("xterm", "", "").with_xtgettcap(b"\x1bP1+r5463\x1b\\"),
+                ColorDepth::TrueColor,
+            ),
+            (env("xterm-kitty", "", ""), ColorDepth::TrueColor),
+            (env("xterm-256color", "", ""), ColorDepth::Indexed256),
+            (env("xterm", "", ""), ColorDepth::Ansi16),
+            (env("dumb", "", ""), ColorDepth::Monochrome),
+            (
+                env("xterm-256color", "", "").with_xtgettcap(b"\x1bP1+r636F6C6F7273=38\x1b\\"),
+                ColorDepth::Ansi16,
+            ),
+            // Sixel support doesn't imply truecolor, and the reported colors
+            // take precedence over the protocol.
+            (
+                env("xterm", "", "").with_device_attributes(b"\x1b[?62;4;22c"),
+                ColorDepth::Ansi16,
+            ),
+            (
+                env("xterm", "", "")
+                    .with_device_attributes(b"\x1b[?62;4;22c")
+                    .with_xtgettcap(b"\x1bP1+r636F6C6F7273=323536\x1b\\"),
+                ColorDepth::Indexed256,
+            ),
+            (
+                env("xterm-kitty", "", "").with_xtgettcap(b"\x1bP1+r636F6C6F7273=323536\x1b\\"),
+                ColorDepth::Indexed256,
+            ),
+        ];
+        for (info, color_depth) in cases {
+            assert_eq!(info.detect().color_depth, color_depth, "{info:?}");
+        }
+    }
+
+    #[test]
+    fn passes_cell_size_through() {
+        let info = TerminalInfo {
+            cell_size: Some(CellSize {
+                width: 9,
+                height: 18,
+            }),
+            ..TerminalInfo::default()
+        };
+        assert_eq!(info.detect().cell_size, info.cell_size);
+    }
+}
@@ -0,0 +1,338 @@
+use std::sync::OnceLock;
+
+use tui::style::Color;
+
+use crate::{
+    color::Rgb,
+    ColorDepth,
+};
+
+/// The dithering algorithm used when reducing the image to the colors
+/// available at the configured [`ColorDepth`].
+///
+/// Error diffusion generally gives the best results for still images, while
+/// the ordered modes only depend on the position of each pixel and so remain
+/// stable from frame to frame when animating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Dither {
+    /// Map each pixel to its nearest color.
+    #[default]
+    None,
+    /// Floyd-Steinberg error diffusion.
+    FloydSteinberg,
+    /// Atkinson error diffusion. Only diffuses 3/4 of the error, which keeps
+    /// more contrast at the cost of detail in highlights & shadows.
+    Atkinson,
+    /// Ordered dithering using an 8x8 Bayer matrix.
+    Bayer,
+    /// Ordered dithering using a 32x32 blue noise threshold map.
+    BlueNoise,
+}
+
+/// The (x offset, y offset, weight) entries used to spread the error of each
+/// pixel for Floyd-Steinberg diffusion. The weights are out of 16.
+const FLOYD_STEINBERG: [(isize, usize, i32); 4] = [(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)];
+
+/// The (x offset, y offset, weight) entries used to spread the error of each
+/// pixel for Atkinson diffusion. The weights are out of 8.
+const ATKINSON: [(isize, usize, i32); 6] = [
+    (1, 0, 1),
+    (2, 0, 1),
+    (-1, 1, 1),
+    (0, 1, 1),
+    (1, 1, 1),
+    (0, 2, 1),
+];
+
+#[rustfmt::skip]
+const BAYER: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+const BLUE_NOISE_SIZE: usize = 32;
+
+impl Dither {
+    /// Reduce a `width` x `height` grid of pixels to the colors available at
+    /// `depth`, returning the color to display for each pixel.
+    pub(crate) fn apply(
+        self,
+        depth: ColorDepth,
+        width: usize,
+        height: usize,
+        pixels: &[Rgb],
+    ) -> Vec<Color> {
+        if depth == ColorDepth::TrueColor {
+            return pixels
+                .iter()
+                .map(|pixel| depth.quantize(*pixel).0)
+                .collect();
+        }
+
+        match self {
+            Dither::None => pixels
+                .iter()
+                .map(|pixel| depth.quantize(*pixel).0)
+                .collect(),
+            Dither::FloydSteinberg => diffuse(depth, width, height, pixels, &FLOYD_STEINBERG, 16),
+            Dither::Atkinson => diffuse(depth, width, height, pixels, &ATKINSON, 8),
+            Dither::Bayer => ordered(depth, width, pixels, |x, y| {
+                (f32::from(BAYER[y % 8][x % 8]) + 0.5) / 64.0
+            }),
+            Dither::BlueNoise => {
+                let noise = blue_noise();
+                ordered(depth, width, pixels, |x, y| {
+                    noise[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE]
+                })
+            }
+        }
+    }
+}
+
+/// Quantize pixels in scanline order, pushing the error of each pixel onto
+/// its unvisited neighbors according to `kernel`.
+fn diffuse(
+    depth: ColorDepth,
+    width: usize,
+    height: usize,
+    pixels: &[Rgb],
+    kernel: &[(isize, usize, i32)],
+    divisor: i32,
+) -> Vec<Color> {
+    let mut errors = vec![[0i32; 3]; pixels.len()];
+    let mut colors = Vec::with_capacity(pixels.len());
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let pixel = pixels[index];
+            let error = errors[index];
+
+            let target = [0, 1, 2].map(|channel| {
+                (i32::from(pixel[channel]) + error[channel] / divisor).clamp(0, 255) as u8
+            });
+            let (color, actual) = depth.quantize(target);
+            colors.push(color);
+
+            let delta =
+                [0, 1, 2].map(|channel| i32::from(target[channel]) - i32::from(actual[channel]));
+            for (dx, dy, weight) in kernel {
+                let (Some(x), y) = (x.checked_add_signed(*dx), y + dy) else {
+                    continue;
+                };
+                if x >= width || y >= height {
+                    continue;
+                }
+
+                let neighbor = &mut errors[y * width + x];
+                for channel in 0..3 {
+                    neighbor[channel] += delta[channel] * weight;
+                }
+            }
+        }
+    }
+
+    colors
+}
+
+/// Quantize pixels after offsetting them by a position dependent threshold in
+/// the range `0.0..1.0`.
+fn ordered(
+    depth: ColorDepth,
+    width: usize,
+    pixels: &[Rgb],
+    threshold: impl Fn(usize, usize) -> f32,
+) -> Vec<Color> {
+    let spread = match depth {
+        ColorDepth::TrueColor => 0.0,
+        ColorDepth::Indexed256 => 48.0,
+        ColorDepth::Ansi16 => 128.0,
+        ColorDepth::Monochrome => 255.0,
+    };
+
+    pixels
+        .iter()
+        .enumerate()
+        .map(|(index, pixel)| {
+            let offset = (threshold(index % width, index / width) - 0.5) * spread;
+            let target = pixel.map(|channel| (f32::from(channel) + offset).clamp(0.0, 255.0) as u8);
+            depth.quantize(target).0
+        })
+        .collect()
+}
+
+/// Get the blue noise threshold map, generating it on first use with the
+/// void-and-cluster method.
+fn blue_noise() -> &'static [f32] {
+    static NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+
+    NOISE.get_or_init(|| {
+        const SIZE: usize = BLUE_NOISE_SIZE;
+        const LEN: usize = SIZE * SIZE;
+        const SIGMA: f32 = 1.5;
+
+        // The gaussian weight for each toroidal offset between two pixels.
+        let mut kernel = vec![0.0f32; LEN];
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let dx = x.min(SIZE - x) as f32;
+                let dy = y.min(SIZE - y) as f32;
+                kernel[y * SIZE + x] = (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+
+        let update = |energy: &mut [f32], index: usize, sign: f32| {
+            let (px, py) = (index % SIZE, index / SIZE);
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    let dx = (x + SIZE - px) % SIZE;
+                    let dy = (y + SIZE - py) % SIZE;
+                    energy[y * SIZE + x] += sign * kernel[dy * SIZE + dx];
+                }
+            }
+        };
+        let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+            (0..LEN)
+                .filter(|index| pattern[*index])
+                .max_by(|l, r| energy[*l].total_cmp(&energy[*r]))
+                .unwrap_or_default()
+        };
+        let largest_void = |pattern: &[bool], energy: &[f32]| {
+            (0..LEN)
+                .filter(|index| !pattern[*index])
+                .min_by(|l, r| energy[*l].total_cmp(&energy[*r]))
+                .unwrap_or_default()
+        };
+
+        // Seed roughly a tenth of the pixels using a fixed lcg so the map is
+        // the same on every run.
+        let mut pattern = vec![false; LEN];
+        let mut energy = vec![0.0f32; LEN];
+        let mut state = 0x2545_f491_u32;
+        let mut ones = 0;
+        while ones < LEN / 10 {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let index = (state >> 8) as usize % LEN;
+            if !pattern[index] {
+                pattern[index] = true;
+                update(&mut energy, index, 1.0);
+                ones += 1;
+            }
+        }
+
+        // Spread the seed points out until they are evenly distributed.
+        loop {
+            let cluster = tightest_cluster(&pattern, &energy);
+            pattern[cluster] = false;
+            update(&mut energy, cluster, -1.0);
+
+            let void = largest_void(&pattern, &energy);
+            pattern[void] = true;
+            update(&mut energy, void, 1.0);
+
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut ranks = vec![0usize; LEN];
+
+        // Rank the seed points by repeatedly removing the tightest cluster.
+        let mut seed = pattern.clone();
+        let mut seed_energy = energy.clone();
+        for rank in (0..ones).rev() {
+            let cluster = tightest_cluster(&seed, &seed_energy);
+            seed[cluster] = false;
+            update(&mut seed_energy, cluster, -1.0);
+            ranks[cluster] = rank;
+        }
+
+        // Rank the remaining pixels by repeatedly filling the largest void.
+        for rank in ones..LEN {
+            let void = largest_void(&pattern, &energy);
+            pattern[void] = true;
+            update(&mut energy, void, 1.0);
+            ranks[void] = rank;
+        }
+
+        ranks
+            .into_iter()
+            .map(|rank| (rank as f32 + 0.5) / LEN as f32)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 32;
+
+    /// The fraction of pixels dithered to white.
+    fn white(colors: &[Color]) -> f32 {
+        colors
+            .iter()
+            .filter(|color| **color == Color::White)
+            .count() as f32
+            / colors.len() as f32
+    }
+
+    #[test]
+    fn dithers_mid_gray_to_half_white() {
+        let pixels = vec![[128, 128, 128]; SIZE * SIZE];
+        for dither in [
+            Dither::FloydSteinberg,
+            Dither::Atkinson,
+            Dither::Bayer,
+            Dither::BlueNoise,
+        ] {
+            let colors = dither.apply(ColorDepth::Monochrome, SIZE, SIZE, &pixels);
+            let white = white(&colors);
+            assert!((0.45..=0.55).contains(&white), "{dither:?}: {white}");
+        }
+
+        let colors = Dither::None.apply(ColorDepth::Monochrome, SIZE, SIZE, &pixels);
+        assert_eq!(white(&colors), 1.0);
+    }
+
+    #[test]
+    fn ordered_dithering_is_stable_between_frames() {
+        let frame = (0..SIZE * SIZE)
+            .map(|index| [(index % 256) as u8; 3])
+            .collect::<Vec<_>>();
+        // The next frame only changes its first row.
+        let mut next = frame.clone();
+        next[..SIZE].fill([255, 0, 0]);
+
+        for dither in [Dither::Bayer, Dither::BlueNoise] {
+            for depth in [
+                ColorDepth::Indexed256,
+                ColorDepth::Ansi16,
+                ColorDepth::Monochrome,
+            ] {
+                let first = dither.apply(depth, SIZE, SIZE, &frame);
+                assert_eq!(dither.apply(depth, SIZE, SIZE, &frame), first);
+                assert_eq!(
+                    dither.apply(depth, SIZE, SIZE, &next)[SIZE..],
+                    first[SIZE..],
+                    "{dither:?} {depth:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_truecolor_undithered() {
+        let pixels = [[1, 2, 3], [4, 5, 6]];
+        assert_eq!(
+            Dither::FloydSteinberg.apply(ColorDepth::TrueColor, 2, 1, &pixels),
+            [Color::Rgb(1, 2, 3), Color::Rgb(4, 5, 6)]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    cell::RefCell,
+    io::{
+        self,
+        Write,
+    },
+};
+
+use tui::layout::Rect;
+
+use crate::{
+    kitty::KittyImages,
+    TerminalInfo,
+};
+
+/// The method used to draw an image to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Protocol {
+    /// Draw the image with colored text cells in the [`tui::buffer::Buffer`].
+    /// Works in every terminal.
+    #[default]
+    Cells,
+    /// Draw the image at full pixel resolution using sixel graphics.
+    /// Requires a [`Graphics`] to write the output.
+    Sixel,
+    /// Draw the image at full pixel resolution using the kitty graphics
+    /// protocol. Images are always sent in full color, and are only
+    /// transmitted once while they remain on screen.
+    /// Requires a [`Graphics`] to write the output.
+    Kitty,
+    /// Draw the image at full pixel resolution using the iTerm2 inline image
+    /// protocol, which is also supported by WezTerm and others. Images are
+    /// always sent in full color.
+    /// Requires a [`Graphics`] to write the output.
+    Iterm2,
+}
+
+/// The terminal multiplexer the process is running under, if any.
+///
+/// Multiplexers don't forward pixel protocols on their own, so their output
+/// has to be wrapped in passthrough sequences. tmux additionally requires the
+/// `allow-passthrough` option to be enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Multiplexer {
+    /// Output is written directly to the terminal.
+    #[default]
+    None,
+    /// Output is wrapped in tmux passthrough sequences.
+    Tmux,
+    /// Output is split into chunks wrapped in screen passthrough sequences.
+    Screen,
+}
+
+/// The maximum number of bytes screen will forward in a single passthrough
+/// sequence.
+const SCREEN_CHUNK_SIZE: usize = 768;
+
+impl Multiplexer {
+    /// Wrap an escape sequence so the multiplexer forwards it to the outer
+    /// terminal.
+    pub(crate) fn wrap(self, sequence: &str) -> String {
+        match self {
+            Multiplexer::None => sequence.to_string(),
+            Multiplexer::Tmux => {
+                format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+            }
+            Multiplexer::Screen => {
+                // Screen ends passthrough at the first string terminator, so
+                // the sequence is split into chunks with every escape at the
+                // end of a chunk, keeping a wrapped terminator from being
+                // seen as one.
+                let mut out = String::with_capacity(sequence.len() + sequence.len() / 64);
+                let mut chunk = String::new();
+                let flush = |out: &mut String, chunk: &mut String| {
+                    out.push_str("\x1bP");
+                    out.push_str(chunk);
+                    out.push_str("\x1b\\");
+                    chunk.clear();
+                };
+                for char in sequence.chars() {
+                    if chunk.len() + char.len_utf8() > SCREEN_CHUNK_SIZE {
+                        flush(&mut out, &mut chunk);
+                    }
+                    chunk.push(char);
+                    if char == '\x1b' {
+                        flush(&mut out, &mut chunk);
+                    }
+                }
+                if !chunk.is_empty() {
+                    flush(&mut out, &mut chunk);
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Collects the escape sequences for images drawn with a pixel protocol.
+///
+/// Pixel graphics can't be represented in a [`tui::buffer::Buffer`], so
+/// images using them only reserve their area in the buffer & queue their
+/// output here. Call [`Graphics::flush`] after each
+/// [`tui::Terminal::draw`] to write the queued output on top of the drawn
+/// buffer.
+///
+/// Kitty images are deleted once they are no longer drawn. Sixel & iTerm2
+/// images can't be deleted, and instead remain until the text beneath them
+/// is redrawn. Their cells are reserved as hidden spaces, so tui redraws them
+/// as soon as the image moves, shrinks or stops being drawn.
+///
+/// [`Graphics::from_env`] detects whether the process is running under a
+/// multiplexer, and wraps the output in the passthrough sequences it needs.
+///
+/// ```no_run
+/// # use std::io;
+/// # use tui::{backend::TestBackend, Terminal};
+/// # use tui_image::{Graphics, Image, PixelFormat, Protocol, RawImage};
+/// # let mut terminal = Terminal::new(TestBackend::new(80, 24))?;
+/// # let image = RawImage::new(&[0; 64 * 64 * 4], 64, 64, 64 * 4, PixelFormat::Rgba);
+/// let graphics = Graphics::from_env();
+/// terminal.draw(|f| {
+///     let image = Image::new(&image)
+///         .protocol(Protocol::Sixel)
+///         .graphics(&graphics);
+///     f.render_widget(image, f.size());
+/// })?;
+/// graphics.flush(&mut io::stdout())?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Graphics {
+    pending: RefCell<Vec<Placement>>,
+    kitty: RefCell<KittyImages>,
+    multiplexer: Multiplexer,
+}
+
+/// Output queued to be written with the cursor at a given cell.
+#[derive(Debug)]
+struct Placement {
+    x: u16,
+    y: u16,
+    payload: String,
+}
+
+impl Graphics {
+    /// Create a queue for the terminal the process is running in, forwarding
+    /// the output through the multiplexer detected from the environment.
+    pub fn from_env() -> Self {
+        Self::default().multiplexer(TerminalInfo::from_env().multiplexer())
+    }
+
+    /// Set the multiplexer the output needs to be forwarded through.
+    /// Defaults to [`Multiplexer::None`].
+    pub fn multiplexer(mut self, multiplexer: Multiplexer) -> Self {
+        self.multiplexer = multiplexer;
+        self
+    }
+
+    /// Queue a sequence to be written with the cursor at the cell (`x`, `y`).
+    pub(crate) fn queue(&self, x: u16, y: u16, payload: String) {
+        self.pending.borrow_mut().push(Placement { x, y, payload });
+    }
+
+    /// Queue an rgba image to be shown with the kitty graphics protocol across
+    /// the cells in `area`.
+    pub(crate) fn queue_kitty(&self, area: Rect, width: u32, height: u32, rgba: &[u8]) {
+        let payload = self
+            .kitty
+            .borrow_mut()
+            .place(width, height, rgba, area.width, area.height);
+        if !payload.is_empty() {
+            self.queue(area.x, area.y, payload);
+        }
+    }
+
+    /// Write all queued output, leaving the cursor where it was.
+    ///
+    /// This marks the end of a frame, so any kitty images drawn in the previous
+    /// frame which weren't drawn since are deleted.
+    pub fn flush(&self, writer: &mut impl Write) -> io::Result<()> {
+        for Placement { x, y, payload } in self.pending.borrow_mut().drain(..) {
+            // The cursor is moved outside of any passthrough so the
+            // multiplexer knows where the image is drawn.
+            write!(
+                writer,
+                "\x1b7\x1b[{};{}H{}\x1b8",
+                y + 1,
+                x + 1,
+                self.multiplexer.wrap(&payload)
+            )?;
+        }
+
+        let deleted = self.kitty.borrow_mut().finish_frame();
+        if !deleted.is_empty() {
+            writer.write_all(self.multiplexer.wrap(&deleted).as_bytes())?;
+        }
+        writer.flush()
+    }
+}
+
+/// Encode bytes as standard padded base64.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | u32::from(*byte) << (16 - index * 8)
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                out.push(char::from(
+                    ALPHABET[(group >> (18 - index * 6)) as usize & 0x3f],
+                ));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_output_unwrapped_without_multiplexer() {
+        assert_eq!(Multiplexer::None.wrap("\x1bPq#0\x1b\\"), "\x1bPq#0\x1b\\");
+    }
+
+    #[test]
+    fn doubles_escapes_for_tmux() {
+        assert_eq!(
+            Multiplexer::Tmux.wrap("\x1bPq#0\x1b\\"),
+            "\x1bPtmux;\x1b\x1bPq#0\x1b\x1b\\\x1b\\"
+        );
+    }
+
+    #[test]
+    fn ends_screen_chunks_at_escapes() {
+        assert_eq!(
+            Multiplexer::Screen.wrap("\x1bPq#0\x1b\\"),
+            "\x1bP\x1b\x1b\\\x1bPPq#0\x1b\x1b\\\x1bP\\\x1b\\"
+        );
+    }
+
+    #[test]
+    fn limits_screen_chunk_size() {
+        // The multibyte character would straddle the end of the third chunk.
+        let sequence = format!("\x1b_G{}é{}\x1b\\", "a".repeat(2301), "b".repeat(1000));
+        let wrapped = Multiplexer::Screen.wrap(&sequence);
+
+        let mut unwrapped = String::new();
+        for chunk in wrapped
+            .strip_prefix("\x1bP")
+            .unwrap()
+            .strip_suffix("\x1b\\")
+            .unwrap()
+            .split("\x1b\\\x1bP")
+        {
+            assert!(chunk.len() <= SCREEN_CHUNK_SIZE, "{}", chunk.len());
+            // An escape can only be the last character of its chunk.
+            assert!(!chunk[..chunk.len() - 1].contains('\x1b'), "{chunk:?}");
+            unwrapped.push_str(chunk);
+        }
+        assert_eq!(unwrapped, sequence);
+    }
+
+    #[test]
+    fn moves_cursor_outside_passthrough() {
+        let graphics = Graphics::default().multiplexer(Multiplexer::Tmux);
+        graphics.queue(2, 4, "\x1bPq\x1b\\".to_string());
+
+        let mut out = vec![];
+        graphics.flush(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b7\x1b[5;3H\x1bPtmux;\x1b\x1bPq\x1b\x1b\\\x1b\\\x1b8"
+        );
+
+        // The queue is emptied once flushed.
+        let mut out = vec![];
+        graphics.flush(&mut out).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn detects_multiplexer_from_env() {
+        assert_eq!(
+            Graphics::from_env().multiplexer,
+            TerminalInfo::from_env().multiplexer()
+        );
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
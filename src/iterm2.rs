@@ -0,0 +1,146 @@
+use crate::{
+    bitmap::Bitmap,
+    graphics::base64,
+};
+
+/// Build the iTerm2 inline image sequence displaying an encoded image file
+/// across `columns` x `rows` cells starting at the cursor.
+pub(crate) fn encode(file: &[u8], columns: u16, rows: u16) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={columns};height={rows};preserveAspectRatio=1:{}\x07",
+        file.len(),
+        base64(file)
+    )
+}
+
+/// Encode an image as a png file.
+#[cfg(feature = "image")]
+pub(crate) fn png(image: &Bitmap) -> Option<Vec<u8>> {
+    use image::{
+        codecs::png::PngEncoder,
+        ColorType,
+        ImageEncoder,
+    };
+
+    let mut png = vec![];
+    PngEncoder::new(&mut png)
+        .write_image(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            ColorType::Rgba8,
+        )
+        .ok()?;
+    Some(png)
+}
+
+/// Encode an image as a png file.
+#[cfg(not(feature = "image"))]
+pub(crate) fn png(image: &Bitmap) -> Option<Vec<u8>> {
+    Some(stored_png(image))
+}
+
+/// Encode an image as an uncompressed png file, for when no png encoder is
+/// available.
+#[cfg(any(not(feature = "image"), test))]
+fn stored_png(image: &Bitmap) -> Vec<u8> {
+    /// The most bytes a stored deflate block can hold.
+    const BLOCK_SIZE: usize = 0xffff;
+
+    // Each row is prefixed with a filter type of 0, leaving it unfiltered.
+    let mut rows = Vec::with_capacity(image.as_bytes().len() + image.height() as usize);
+    for row in image.as_bytes().chunks((image.width() as usize * 4).max(1)) {
+        rows.push(0);
+        rows.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = rows.chunks(BLOCK_SIZE).collect::<Vec<_>>();
+    for (index, block) in blocks.iter().enumerate() {
+        let len = block.len() as u16;
+        zlib.push(u8::from(index + 1 == blocks.len()));
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    if blocks.is_empty() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    zlib.extend_from_slice(&adler32(&rows).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend_from_slice(&image.width().to_be_bytes());
+    header.extend_from_slice(&image.height().to_be_bytes());
+    // 8-bit rgba, with the default compression, filtering & no interlacing.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", &header), (b"IDAT", &zlib), (b"IEND", &vec![])] {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        png.extend_from_slice(&crc32(kind.iter().chain(data.iter())).to_be_bytes());
+    }
+    png
+}
+
+#[cfg(any(not(feature = "image"), test))]
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + u32::from(*byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
+
+#[cfg(any(not(feature = "image"), test))]
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    !bytes.fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ u32::from(*byte), |crc, _| {
+            if crc & 1 == 1 {
+                crc >> 1 ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_inline_file() {
+        assert_eq!(
+            encode(b"hello", 4, 2),
+            "\x1b]1337;File=inline=1;size=5;width=4;height=2;preserveAspectRatio=1:aGVsbG8=\x07"
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn stored_png_round_trips() {
+        let image = Bitmap::from_fn(3, 2, |x, y| [x as u8 * 80, y as u8 * 200, 7, 255 - x as u8]);
+        let png = stored_png(&image);
+
+        let decoded = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(decoded.dimensions(), (3, 2));
+        assert_eq!(decoded.as_raw(), image.as_bytes());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn stored_png_round_trips_across_blocks() {
+        // Large enough to need several stored deflate blocks.
+        let image = Bitmap::from_fn(200, 100, |x, y| [x as u8, y as u8, (x ^ y) as u8, 255]);
+        let png = stored_png(&image);
+
+        let decoded = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(decoded.as_raw(), image.as_bytes());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::{
+        hash_map::DefaultHasher,
+        HashMap,
+    },
+    fmt::Write,
+    hash::{
+        Hash,
+        Hasher,
+    },
+};
+
+use crate::graphics::base64;
+
+/// The maximum size of each base64 chunk when transmitting image data.
+const CHUNK_SIZE: usize = 4096;
+
+/// Tracks the images transmitted to the terminal with the kitty graphics
+/// protocol so they can be placed again without resending their data, and
+/// deleted once they are no longer drawn.
+///
+/// Each placement of an image within a frame gets its own placement id, so
+/// the same image can be shown in several places at once. Placement ids are
+/// reused in the next frame, which moves the existing placements rather than
+/// adding new ones.
+#[derive(Debug, Default)]
+pub(crate) struct KittyImages {
+    ids: HashMap<u64, u32>,
+    next_id: u32,
+    /// The number of placements of each image in the current frame.
+    placed: HashMap<u32, u32>,
+    /// The number of placements of each image in the previous frame.
+    shown: HashMap<u32, u32>,
+}
+
+impl KittyImages {
+    /// Build the sequence to display `rgba` across `columns` x `rows` cells
+    /// starting at the cursor, transmitting it first if the terminal doesn't
+    /// already have a copy. Empty images aren't shown.
+    pub(crate) fn place(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+        columns: u16,
+        rows: u16,
+    ) -> String {
+        if width == 0 || height == 0 || rgba.is_empty() {
+            return String::new();
+        }
+
+        let mut hasher = DefaultHasher::new();
+        (width, height, rgba).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut out = String::new();
+        let id = match self.ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                self.next_id += 1;
+                let id = self.next_id;
+                self.ids.insert(key, id);
+
+                let data = base64(rgba);
+                let mut chunks = data.as_bytes().chunks(CHUNK_SIZE).peekable();
+                let mut first = true;
+                while let Some(chunk) = chunks.next() {
+                    let more = u8::from(chunks.peek().is_some());
+                    let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+                    if first {
+                        let _ = write!(
+                            out,
+                            "\x1b_Ga=t,f=32,s={width},v={height},i={id},q=2,m={more};{chunk}\x1b\\"
+                        );
+                    } else {
+                        let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+                    }
+                    first = false;
+                }
+                id
+            }
+        };
+
+        let placement = self.placed.entry(id).or_default();
+        *placement += 1;
+        let _ = write!(
+            out,
+            "\x1b_Ga=p,i={id},p={placement},c={columns},r={rows},C=1,q=2\x1b\\"
+        );
+
+        out
+    }
+
+    /// Finish the current frame, returning the sequence deleting any images
+    /// or placements which were shown in the previous frame but not placed in
+    /// this one.
+    pub(crate) fn finish_frame(&mut self) -> String {
+        let mut shown = self.shown.iter().collect::<Vec<_>>();
+        shown.sort_unstable();
+
+        let mut out = String::new();
+        for (id, shown) in shown {
+            match self.placed.get(id) {
+                None => {
+                    let _ = write!(out, "\x1b_Ga=d,d=I,i={id},q=2\x1b\\");
+                }
+                Some(placed) => {
+                    for placement in placed + 1..=*shown {
+                        let _ = write!(out, "\x1b_Ga=d,d=i,i={id},p={placement},q=2\x1b\\");
+                    }
+                }
+            }
+        }
+        self.ids.retain(|_, id| self.placed.contains_key(id));
+
+        self.shown = std::mem::take(&mut self.placed);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXEL: [u8; 4] = [255, 0, 0, 255];
+
+    #[test]
+    fn transmits_in_chunks() {
+        let rgba = PIXEL.repeat(32 * 32);
+        let data = base64(&rgba);
+
+        let mut images = KittyImages::default();
+        assert_eq!(
+            images.place(32, 32, &rgba, 4, 2),
+            format!(
+                "\x1b_Ga=t,f=32,s=32,v=32,i=1,q=2,m=1;{}\x1b\\\
+                 \x1b_Gm=0;{}\x1b\\\
+                 \x1b_Ga=p,i=1,p=1,c=4,r=2,C=1,q=2\x1b\\",
+                &data[..CHUNK_SIZE],
+                &data[CHUNK_SIZE..]
+            )
+        );
+    }
+
+    #[test]
+    fn places_again_without_transmitting() {
+        let mut images = KittyImages::default();
+        images.place(1, 1, &PIXEL, 1, 1);
+        assert_eq!(images.finish_frame(), "");
+
+        assert_eq!(
+            images.place(1, 1, &PIXEL, 2, 3),
+            "\x1b_Ga=p,i=1,p=1,c=2,r=3,C=1,q=2\x1b\\"
+        );
+        assert_eq!(images.finish_frame(), "");
+    }
+
+    #[test]
+    fn deletes_images_no_longer_placed() {
+        let mut images = KittyImages::default();
+        images.place(1, 1, &PIXEL, 1, 1);
+        assert_eq!(images.finish_frame(), "");
+        assert_eq!(images.finish_frame(), "\x1b_Ga=d,d=I,i=1,q=2\x1b\\");
+        assert_eq!(images.finish_frame(), "");
+
+        // The image has to be transmitted again once it's deleted.
+        assert!(images.place(1, 1, &PIXEL, 1, 1).starts_with("\x1b_Ga=t,"));
+    }
+
+    #[test]
+    fn places_identical_images_separately() {
+        let mut images = KittyImages::default();
+        assert_eq!(
+            images.place(1, 1, &PIXEL, 1, 1),
+            "\x1b_Ga=t,f=32,s=1,v=1,i=1,q=2,m=0;/wAA/w==\x1b\\\
+             \x1b_Ga=p,i=1,p=1,c=1,r=1,C=1,q=2\x1b\\"
+        );
+        assert_eq!(
+            images.place(1, 1, &PIXEL, 1, 1),
+            "\x1b_Ga=p,i=1,p=2,c=1,r=1,C=1,q=2\x1b\\"
+        );
+        assert_eq!(images.finish_frame(), "");
+
+        images.place(1, 1, &PIXEL, 1, 1);
+        assert_eq!(images.finish_frame(), "\x1b_Ga=d,d=i,i=1,p=2,q=2\x1b\\");
+    }
+
+    #[test]
+    fn skips_empty_images() {
+        let mut images = KittyImages::default();
+        assert_eq!(images.place(0, 0, &[], 1, 1), "");
+        assert_eq!(images.finish_frame(), "");
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    bitmap::Bitmap,
+    resample,
+    Filter,
+    PixelView,
+};
+
+/// How the image is scaled to the available area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Fit {
+    /// Scale the image down to fit within the area, preserving its aspect
+    /// ratio. Smaller images are only scaled up if [`crate::Image::upscale`]
+    /// is set.
+    #[default]
+    Contain,
+    /// Scale the image to cover the whole area, preserving its aspect ratio
+    /// and cropping whatever overflows.
+    Cover,
+    /// Stretch the image to exactly fill the area, ignoring its aspect ratio.
+    Fill,
+    /// Keep the image at its original size, cropping whatever overflows. The
+    /// image is only stretched as needed to correct for the aspect ratio of
+    /// the pixels drawn in each cell.
+    None,
+}
+
+/// Where the image is placed along an axis of the available area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Align {
+    /// Place the image at the left or top.
+    Start,
+    /// Place the image in the middle.
+    #[default]
+    Center,
+    /// Place the image at the right or bottom.
+    End,
+}
+
+impl Align {
+    /// The offset of `used` units placed within `available` units.
+    pub(crate) fn offset(self, available: u32, used: u32) -> u32 {
+        let free = available.saturating_sub(used);
+        match self {
+            Align::Start => 0,
+            Align::Center => free / 2,
+            Align::End => free,
+        }
+    }
+}
+
+impl Fit {
+    /// Scale `image` to the available `width` x `height` pixels, cropping it so
+    /// it never exceeds them. The part of the image which is kept when
+    /// cropping depends on the alignment along each axis.
+    ///
+    /// `stretch` is the amount the image is widened by to keep its proportions
+    /// when the target pixels aren't square, e.g. `2.0` when each target pixel
+    /// is twice as tall as it is wide.
+    pub(crate) fn apply(
+        self,
+        image: &dyn PixelView,
+        (width, height): (u32, u32),
+        (horizontal, vertical): (Align, Align),
+        stretch: f64,
+        upscale: bool,
+        filter: Filter,
+    ) -> Bitmap {
+        let (image_width, image_height) = image.dimensions();
+        if image_width == 0 || image_height == 0 {
+            return Bitmap::default();
+        }
+
+        let source_width = f64::from(image_width) * stretch;
+        let source_height = f64::from(image_height);
+        let scaled = |scale: f64| {
+            (
+                ((source_width * scale).round() as u32).max(1),
+                ((source_height * scale).round() as u32).max(1),
+            )
+        };
+        let resize = |(scaled_width, scaled_height): (u32, u32)| {
+            if (scaled_width, scaled_height) == (image_width, image_height) {
+                Bitmap::from_fn(image_width, image_height, |x, y| image.rgba(x, y))
+            } else {
+                resample::resize(image, scaled_width, scaled_height, filter)
+            }
+        };
+
+        match self {
+            Fit::Contain => {
+                let mut scale = f64::min(
+                    f64::from(width) / source_width,
+                    f64::from(height) / source_height,
+                );
+                if !upscale {
+                    scale = scale.min(1.0);
+                }
+
+                let (scaled_width, scaled_height) = scaled(scale);
+                resize((scaled_width.min(width), scaled_height.min(height)))
+            }
+            Fit::Cover => {
+                let scale = f64::max(
+                    f64::from(width) / source_width,
+                    f64::from(height) / source_height,
+                );
+
+                let (scaled_width, scaled_height) = scaled(scale);
+                crop(
+                    &resize((scaled_width.max(width), scaled_height.max(height))),
+                    (width, height),
+                    (horizontal, vertical),
+                )
+            }
+            Fit::Fill => resize((width, height)),
+            Fit::None => crop(
+                &resize(scaled(1.0)),
+                (width, height),
+                (horizontal, vertical),
+            ),
+        }
+    }
+}
+
+/// Crop `image` to at most `width` x `height` pixels. The cropped region is
+/// placed against the side of the image matching its alignment, so that
+/// e.g. [`Align::Start`] keeps the left or top of the image.
+fn crop(
+    image: &Bitmap,
+    (width, height): (u32, u32),
+    (horizontal, vertical): (Align, Align),
+) -> Bitmap {
+    let (image_width, image_height) = (image.width(), image.height());
+    let (width, height) = (width.min(image_width), height.min(image_height));
+
+    image.crop(
+        horizontal.offset(image_width, width),
+        vertical.offset(image_height, height),
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        PixelFormat,
+        RawImage,
+    };
+
+    /// Fit a `width` x `height` image to `target`, returning the dimensions
+    /// of the result.
+    fn fit(fit: Fit, (width, height): (u32, u32), target: (u32, u32), upscale: bool) -> (u32, u32) {
+        let pixels = vec![u8::MAX; width as usize * height as usize * 4];
+        let image = RawImage::new(
+            &pixels,
+            width,
+            height,
+            width as usize * 4,
+            PixelFormat::Rgba,
+        );
+        let fitted = fit.apply(
+            &image,
+            target,
+            (Align::Center, Align::Center),
+            1.0,
+            upscale,
+            Filter::Triangle,
+        );
+        (fitted.width(), fitted.height())
+    }
+
+    #[test]
+    fn contains_image() {
+        assert_eq!(fit(Fit::Contain, (100, 50), (40, 40), false), (40, 20));
+        assert_eq!(fit(Fit::Contain, (50, 100), (40, 40), false), (20, 40));
+        assert_eq!(fit(Fit::Contain, (10, 5), (40, 40), false), (10, 5));
+        assert_eq!(fit(Fit::Contain, (10, 5), (40, 40), true), (40, 20));
+        assert_eq!(fit(Fit::Contain, (0, 5), (40, 40), true), (0, 0));
+    }
+
+    #[test]
+    fn covers_area() {
+        assert_eq!(fit(Fit::Cover, (100, 50), (40, 40), false), (40, 40));
+        assert_eq!(fit(Fit::Cover, (10, 5), (40, 40), false), (40, 40));
+        assert_eq!(fit(Fit::Cover, (40, 40), (40, 10), false), (40, 10));
+    }
+
+    #[test]
+    fn fills_area() {
+        assert_eq!(fit(Fit::Fill, (100, 50), (40, 40), false), (40, 40));
+        assert_eq!(fit(Fit::Fill, (10, 5), (30, 20), false), (30, 20));
+    }
+
+    #[test]
+    fn keeps_original_size() {
+        assert_eq!(fit(Fit::None, (100, 50), (40, 40), false), (40, 40));
+        assert_eq!(fit(Fit::None, (10, 5), (40, 40), true), (10, 5));
+    }
+
+    #[test]
+    fn stretches_for_pixel_aspect_ratio() {
+        let pixels = vec![u8::MAX; 10 * 10 * 4];
+        let image = RawImage::new(&pixels, 10, 10, 40, PixelFormat::Rgba);
+        let fitted = Fit::Contain.apply(
+            &image,
+            (40, 40),
+            (Align::Center, Align::Center),
+            2.0,
+            true,
+            Filter::Triangle,
+        );
+        assert_eq!((fitted.width(), fitted.height()), (40, 20));
+    }
+
+    #[test]
+    fn crops_to_alignment() {
+        // Each pixel's red channel is its column.
+        let pixels = (0..8).flat_map(|x| [x, 0, 0, 255]).collect::<Vec<_>>();
+        let image = RawImage::new(&pixels, 8, 1, 32, PixelFormat::Rgba);
+
+        for (align, start) in [(Align::Start, 0), (Align::Center, 2), (Align::End, 4)] {
+            for fit in [Fit::None, Fit::Cover] {
+                let cropped = fit.apply(
+                    &image,
+                    (4, 1),
+                    (align, Align::Center),
+                    1.0,
+                    false,
+                    Filter::Nearest,
+                );
+                let columns = (0..4).map(|x| cropped.get(x, 0)[0]).collect::<Vec<_>>();
+                assert_eq!(
+                    columns,
+                    [start, start + 1, start + 2, start + 3],
+                    "{fit:?} {align:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_by_alignment() {
+        assert_eq!(Align::Start.offset(10, 3), 0);
+        assert_eq!(Align::Center.offset(10, 3), 3);
+        assert_eq!(Align::End.offset(10, 3), 7);
+        assert_eq!(Align::End.offset(3, 10), 0);
+    }
+}
@@ -1,52 +1,309 @@
-use image::{
-    imageops::FilterType,
-    DynamicImage,
-    GenericImageView,
+#[cfg(feature = "tokio")]
+mod async_load;
+mod bitmap;
+mod cell_size;
+mod cells;
+mod color;
+mod detect;
+mod dither;
+mod graphics;
+mod iterm2;
+mod kitty;
+mod layout;
+#[cfg(feature = "image")]
+mod loader;
+mod prepared;
+mod raw;
+mod resample;
+mod sixel;
+mod source;
+mod state;
+
+use std::{
+    error::Error,
+    mem,
+};
+
+#[cfg(feature = "tokio")]
+pub use async_load::load_image;
+use bitmap::Bitmap;
+pub use cell_size::{
+    CellSize,
+    CELL_SIZE_QUERY,
+    WINDOW_SIZE_QUERY,
+};
+pub use cells::{
+    CellEncoding,
+    GlyphSet,
+};
+pub use color::ColorDepth;
+pub use detect::{
+    xtgettcap_query,
+    Capabilities,
+    TerminalInfo,
+    DA1_QUERY,
+};
+pub use dither::Dither;
+pub use graphics::{
+    Graphics,
+    Multiplexer,
+    Protocol,
+};
+pub use layout::{
+    Align,
+    Fit,
 };
+#[cfg(feature = "image")]
+pub use loader::{
+    LoadHandle,
+    LoadSource,
+    Loader,
+};
+pub use prepared::PreparedImage;
+pub use raw::{
+    PixelFormat,
+    RawImage,
+};
+pub use resample::Filter;
+#[cfg(feature = "image")]
+pub use source::View;
+pub use source::{
+    ImageSource,
+    PixelView,
+};
+pub use state::ImageState;
+use state::ResizeKey;
 use tui::{
-    buffer::Buffer,
-    layout::Rect,
+    buffer::{
+        Buffer,
+        Cell,
+    },
+    layout::{
+        Alignment,
+        Rect,
+    },
     style::{
         Color,
+        Modifier,
         Style,
     },
+    text::{
+        Span,
+        Spans,
+        Text,
+    },
     widgets::{
         Block,
+        Paragraph,
+        StatefulWidget,
         Widget,
     },
 };
 
 /// A tui widget for displaying images.
-/// All images will be displayed centered vertically & horizontally on the
-/// available space.
+/// Images are displayed centered vertically & horizontally on the available
+/// space by default.
 ///
-/// No support for transparancy is provided.
+/// Transparent pixels are alpha blended against the matte color, which
+/// defaults to the background color of the widget's style. In overlay mode
+/// they are instead blended against the cells already drawn in the buffer.
 pub struct Image<'a> {
-    image: &'a DynamicImage,
+    source: Box<dyn ImageSource + 'a>,
+    placeholder: Text<'a>,
+    error_symbol: Span<'a>,
     block: Option<Block<'a>>,
     style: Style,
+    matte: Option<Color>,
+    overlay: bool,
+    color_depth: ColorDepth,
+    dither: Dither,
+    protocol: Protocol,
+    cell_encoding: CellEncoding,
+    graphics: Option<&'a Graphics>,
+    cell_size: CellSize,
+    fit: Fit,
+    horizontal_alignment: Align,
+    vertical_alignment: Align,
     scale_up: bool,
-    filter_mode: FilterType,
+    filter_mode: Filter,
+    version: u64,
 }
 
 impl<'a> Image<'a> {
-    pub fn new(image: &DynamicImage) -> Image {
+    /// Create a widget drawing `source`, which may be borrowed, owned or
+    /// shared. See [`ImageSource`] for the supported types.
+    pub fn new(source: impl ImageSource + 'a) -> Self {
         Image {
-            image,
+            source: Box::new(source),
+            placeholder: Text::default(),
+            error_symbol: Span::styled("🖼", Style::default().fg(Color::Red)),
             block: None,
             style: Style::default(),
+            matte: None,
+            overlay: false,
+            color_depth: ColorDepth::TrueColor,
+            dither: Dither::None,
+            protocol: Protocol::Cells,
+            cell_encoding: CellEncoding::HalfBlock,
+            graphics: None,
+            cell_size: CellSize::default(),
+            fit: Fit::Contain,
+            horizontal_alignment: Align::Center,
+            vertical_alignment: Align::Center,
             scale_up: false,
-            filter_mode: FilterType::Lanczos3,
+            filter_mode: Filter::Lanczos3,
+            version: 0,
         }
     }
 
+    /// Draw an image being loaded by a [`Loader`], showing the placeholder
+    /// until it has loaded.
+    #[cfg(feature = "image")]
+    pub fn from_handle(handle: &'a LoadHandle) -> Self {
+        Image::new(handle)
+    }
+
+    /// Decode an image from a file or bytes, detecting its format from its
+    /// contents. If it fails to load, the error is drawn in place of the
+    /// image.
+    ///
+    /// The image is decoded on every call, so to draw it repeatedly keep a
+    /// handle from [`LoadHandle::load`] & draw it with [`Image::from_handle`]
+    /// instead.
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// # use tui::{backend::TestBackend, Terminal};
+    /// # use tui_image::{Image, LoadHandle};
+    /// # let mut terminal = Terminal::new(TestBackend::new(80, 24))?;
+    /// let handle = LoadHandle::load("photo.jpg");
+    /// terminal.draw(|f| f.render_widget(Image::from_handle(&handle), f.size()))?;
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn load(source: impl Into<LoadSource>) -> Self {
+        Image::new(LoadHandle::load(source))
+    }
+
     /// Set the style of the background around the displayed image.
     pub fn style(mut self, style: Style) -> Self {
         self.style = style;
         self
     }
 
-    /// Indicate if the image should be scaled up to fit the available area.
+    /// Set the color transparent pixels are blended against.
+    /// Defaults to the background color of the style, or black if the style
+    /// has no background color.
+    pub fn matte(mut self, matte: Color) -> Self {
+        self.matte = Some(matte);
+        self
+    }
+
+    /// Indicate if the image should be drawn over the existing contents of the
+    /// buffer. When enabled, the style is not applied to the area and
+    /// transparent pixels are blended against the colors of the underlying
+    /// cells, falling back to the matte color where those are unset. Cells
+    /// covered only by fully transparent pixels are left untouched.
+    /// Defaults to `false`.
+    pub fn overlay(mut self, overlay: bool) -> Self {
+        self.overlay = overlay;
+        self
+    }
+
+    /// Set the range of colors used to display the image.
+    /// Defaults to [`ColorDepth::TrueColor`].
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.color_depth = color_depth;
+        self
+    }
+
+    /// Set the dithering algorithm used when reducing the image to the colors
+    /// available at the color depth. Has no effect for
+    /// [`ColorDepth::TrueColor`].
+    /// Defaults to [`Dither::None`].
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Set the protocol, color depth & cell size from the detected
+    /// capabilities of the terminal.
+    ///
+    /// ```no_run
+    /// # use tui_image::{Graphics, Image, PixelFormat, RawImage, TerminalInfo};
+    /// # let image = RawImage::new(&[0; 64 * 64 * 4], 64, 64, 64 * 4, PixelFormat::Rgba);
+    /// let capabilities = TerminalInfo::from_env().detect();
+    /// let graphics = Graphics::from_env();
+    /// let image = Image::new(&image)
+    ///     .capabilities(capabilities)
+    ///     .graphics(&graphics);
+    /// ```
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.protocol = capabilities.protocol;
+        self.color_depth = capabilities.color_depth;
+        if let Some(cell_size) = capabilities.cell_size {
+            self.cell_size = cell_size;
+        }
+        self
+    }
+
+    /// Set the method used to draw the image.
+    /// Pixel protocols also require [`Image::graphics`] to be set, and fall
+    /// back to [`Protocol::Cells`] otherwise.
+    /// Defaults to [`Protocol::Cells`].
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Set the glyphs used to draw the image with [`Protocol::Cells`].
+    /// Defaults to [`CellEncoding::HalfBlock`].
+    pub fn cell_encoding(mut self, cell_encoding: CellEncoding) -> Self {
+        self.cell_encoding = cell_encoding;
+        self
+    }
+
+    /// Set where the output of pixel protocols is queued.
+    pub fn graphics(mut self, graphics: &'a Graphics) -> Self {
+        self.graphics = Some(graphics);
+        self
+    }
+
+    /// Set the size of a terminal cell in pixels. This is used to keep the
+    /// image's proportions when drawing with cells, for which only the aspect
+    /// ratio matters, e.g. 10x22 for a font with a 1:2.2 aspect ratio. Pixel
+    /// protocols also use it to size the image.
+    /// Defaults to 10x20.
+    pub fn cell_size(mut self, cell_size: CellSize) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    /// Set how the image is scaled to the available area.
+    /// Defaults to [`Fit::Contain`].
+    pub fn fit(mut self, fit: Fit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Set where the image is placed horizontally within the available area,
+    /// and which part of it is kept when cropping.
+    /// Defaults to [`Align::Center`].
+    pub fn horizontal_alignment(mut self, alignment: Align) -> Self {
+        self.horizontal_alignment = alignment;
+        self
+    }
+
+    /// Set where the image is placed vertically within the available area,
+    /// and which part of it is kept when cropping.
+    /// Defaults to [`Align::Center`].
+    pub fn vertical_alignment(mut self, alignment: Align) -> Self {
+        self.vertical_alignment = alignment;
+        self
+    }
+
+    /// Indicate if the image should be scaled up to fit the available area
+    /// when using [`Fit::Contain`].
     /// Defaults to `false`.
     pub fn upscale(mut self, upscale: bool) -> Self {
         self.scale_up = upscale;
@@ -58,18 +315,66 @@ impl<'a> Image<'a> {
         self
     }
 
+    /// Set the text shown centered in the area while there is no image to
+    /// draw, e.g. while it is loading.
+    /// Defaults to no text.
+    pub fn placeholder(mut self, placeholder: impl Into<Text<'a>>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    /// Set the symbol shown above the error when the image fails to load.
+    /// Defaults to a red `🖼`.
+    pub fn error_symbol(mut self, symbol: impl Into<Span<'a>>) -> Self {
+        self.error_symbol = symbol.into();
+        self
+    }
+
     /// Set the filter mode for upscaling/downscaling.
-    /// Defaults to [`FilterType::Lanczos3`].
-    pub fn filter_mode(mut self, filter_mode: FilterType) -> Self {
-        self.filter_mode = filter_mode;
+    /// Defaults to [`Filter::Lanczos3`].
+    pub fn filter_mode(mut self, filter_mode: impl Into<Filter>) -> Self {
+        self.filter_mode = filter_mode.into();
+        self
+    }
+
+    /// Set the version of the image's contents. When drawn with an
+    /// [`ImageState`], the cached scaled image is only reused while the
+    /// version stays the same, so bump it whenever the image changes without
+    /// moving, e.g. when it is modified in place.
+    /// Defaults to `0`.
+    pub fn version(mut self, version: u64) -> Self {
+        self.version = version;
         self
     }
+
+    /// Scale & encode the image into cells for `area` ahead of time, so it can
+    /// be drawn without any image processing.
+    ///
+    /// The image is always prepared with [`Protocol::Cells`]. In overlay mode,
+    /// transparent pixels are blended against the matte color since the
+    /// underlying cells aren't known yet.
+    pub fn prepare(mut self, area: Rect) -> PreparedImage {
+        self.graphics = None;
+
+        let mut buffer = PreparedImage::buffer(area);
+        Widget::render(self, area, &mut buffer);
+        PreparedImage::new(buffer)
+    }
 }
 
 impl Widget for Image<'_> {
-    fn render(mut self, area: Rect, buf: &mut Buffer) {
-        const HALF_BLOCK: char = '▄';
-        buf.set_style(area, self.style);
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        StatefulWidget::render(self, area, buf, &mut ImageState::default());
+    }
+}
+
+impl StatefulWidget for Image<'_> {
+    type State = ImageState;
+
+    fn render(mut self, area: Rect, buf: &mut Buffer, state: &mut ImageState) {
+        if !self.overlay {
+            buf.set_style(area, self.style);
+        }
 
         let area = match self.block.take() {
             Some(block) => {
@@ -80,40 +385,740 @@ impl Widget for Image<'_> {
             None => area,
         };
 
-        let mut image_area = area;
-        if image_area.height == 0 || image_area.width == 0 {
+        if area.height == 0 || area.width == 0 {
             return;
         }
 
-        if !self.scale_up {
-            image_area.width = u32::from(area.width).min(self.image.width()) as u16;
-            image_area.height = u32::from(area.height * 2).min(self.image.height()) as u16;
+        let Some(image) = self.source.view() else {
+            match self.source.error() {
+                Some(error) => self.render_error(error, area, buf),
+                None => render_centered(self.placeholder.clone(), area, buf),
+            }
+            return;
+        };
+
+        let matte = self
+            .matte
+            .or(self.style.bg)
+            .and_then(color::to_rgb)
+            .unwrap_or_default();
+
+        match (self.protocol, self.graphics) {
+            (Protocol::Sixel, Some(graphics)) => {
+                self.render_sixel(image, area, buf, matte, graphics, state)
+            }
+            (Protocol::Kitty, Some(graphics)) => {
+                self.render_kitty(image, area, matte, graphics, state)
+            }
+            (Protocol::Iterm2, Some(graphics)) => {
+                self.render_iterm2(image, area, buf, matte, graphics, state)
+            }
+            _ => self.render_cells(image, area, buf, matte, state),
+        }
+    }
+}
+
+impl Image<'_> {
+    /// Draw the error symbol, the name of the image & the error it failed to
+    /// load with, centered in the area.
+    fn render_error(&self, error: &dyn Error, area: Rect, buf: &mut Buffer) {
+        let mut lines = vec![Spans::from(self.error_symbol.clone())];
+        if let Some(name) = self.source.name() {
+            lines.push(Spans::from(Span::styled(
+                name.to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+        }
+        lines.extend(
+            wrap(&error.to_string(), usize::from(area.width))
+                .into_iter()
+                .map(Spans::from),
+        );
+
+        render_centered(Text::from(lines), area, buf);
+    }
+
+    /// Draw the image into the buffer using the glyphs of the cell encoding.
+    fn render_cells(
+        &self,
+        source: &dyn PixelView,
+        area: Rect,
+        buf: &mut Buffer,
+        matte: color::Rgb,
+        state: &mut ImageState,
+    ) {
+        let (cell_width, cell_height) = self.cell_encoding.pixels_per_cell();
+
+        // Each pixel drawn by the cell encoding covers a fraction of the cell,
+        // so they are only square if the cell has the same proportions as the
+        // encoding's grid.
+        let stretch = (f64::from(self.cell_size.height.max(1)) * cell_width as f64)
+            / (f64::from(self.cell_size.width.max(1)) * cell_height as f64);
+        let (image, cells) = self.fit_to_cells(
+            source,
+            area,
+            cell_width as u32,
+            cell_height as u32,
+            stretch,
+            state,
+        );
+
+        let (columns, rows) = (usize::from(cells.width), usize::from(cells.height));
+        let (x_start, y_start) = (cells.x, cells.y);
+
+        let width = columns * cell_width;
+        let height = rows * cell_height;
+
+        // The image may not fill its last row or column of cells, e.g. when
+        // drawing an odd number of rows with half blocks. The spare pixels are
+        // placed according to the alignment, so the image stays flush with
+        // the side it's aligned to.
+        let x_offset = self
+            .horizontal_alignment
+            .offset(width as u32, image.width()) as usize;
+        let y_offset = self
+            .vertical_alignment
+            .offset(height as u32, image.height()) as usize;
+        let image_x = x_offset..x_offset + image.width() as usize;
+        let image_y = y_offset..y_offset + image.height() as usize;
+
+        // Composite every pixel first so the whole image can be dithered at
+        // once. In overlay mode, cells covered only by fully transparent
+        // pixels are skipped entirely.
+        let mut pixels = vec![matte; width * height];
+        let mut skip = vec![self.overlay; columns * rows];
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = if image_x.contains(&x) && image_y.contains(&y) {
+                    image.get((x - x_offset) as u32, (y - y_offset) as u32)
+                } else {
+                    [0; 4]
+                };
+
+                let (column, row) = (x / cell_width, y / cell_height);
+                let under = if self.overlay {
+                    if pixel[3] != 0 {
+                        skip[row * columns + column] = false;
+                    }
+
+                    let cell = buf.get(x_start + column as u16, y_start + row as u16);
+                    let (top, bottom) = underlying_colors(cell);
+                    let under = if (y % cell_height) * 2 < cell_height {
+                        top
+                    } else {
+                        bottom
+                    };
+                    color::to_rgb(under).unwrap_or(matte)
+                } else {
+                    matte
+                };
+
+                pixels[y * width + x] = color::blend(pixel, under);
+            }
+        }
+
+        let depth = if self.cell_encoding.monochrome() {
+            ColorDepth::Monochrome
         } else {
-            image_area.height *= 2;
+            self.color_depth
+        };
+        let colors = self.dither.apply(depth, width, height, &pixels);
+
+        let mut cell_pixels = Vec::with_capacity(cell_width * cell_height);
+        let mut cell_source = Vec::with_capacity(cell_width * cell_height);
+        for row in 0..rows {
+            for column in 0..columns {
+                if skip[row * columns + column] {
+                    continue;
+                }
+
+                cell_pixels.clear();
+                cell_source.clear();
+                for y in row * cell_height..(row + 1) * cell_height {
+                    for x in column * cell_width..(column + 1) * cell_width {
+                        let color = colors[y * width + x];
+                        cell_pixels.push(color::to_rgb(color).unwrap_or(matte));
+                        cell_source.push(pixels[y * width + x]);
+                    }
+                }
+
+                let encoded = self.cell_encoding.encode(&cell_pixels, &cell_source, matte);
+                let cell = buf
+                    .get_mut(x_start + column as u16, y_start + row as u16)
+                    .set_char(encoded.symbol);
+                if let Some(fg) = encoded.fg {
+                    cell.set_fg(self.color_depth.quantize(fg).0);
+                }
+                if let Some(bg) = encoded.bg {
+                    cell.set_bg(self.color_depth.quantize(bg).0);
+                }
+            }
+        }
+    }
+
+    /// Fit the image to the pixels covered by `area`, given the size of each
+    /// cell in pixels & how much the image needs to be widened to correct for
+    /// non-square pixels. Returns the scaled image along with the rect of the
+    /// cells it covers.
+    fn fit_to_cells<'s>(
+        &self,
+        source: &dyn PixelView,
+        area: Rect,
+        cell_width: u32,
+        cell_height: u32,
+        stretch: f64,
+        state: &'s mut ImageState,
+    ) -> (&'s Bitmap, Rect) {
+        let cell_width = cell_width.max(1);
+        let cell_height = cell_height.max(1);
+
+        let key = ResizeKey {
+            source: source.address(),
+            source_dimensions: source.dimensions(),
+            version: self.version,
+            target: (
+                u32::from(area.width) * cell_width,
+                u32::from(area.height) * cell_height,
+            ),
+            stretch,
+            fit: self.fit,
+            alignment: (self.horizontal_alignment, self.vertical_alignment),
+            upscale: self.scale_up,
+            filter: self.filter_mode,
+        };
+        let image = state.resized(key, || {
+            self.fit.apply(
+                source,
+                key.target,
+                key.alignment,
+                stretch,
+                self.scale_up,
+                self.filter_mode,
+            )
+        });
+
+        let columns = (image.width().div_ceil(cell_width) as u16).min(area.width);
+        let rows = (image.height().div_ceil(cell_height) as u16).min(area.height);
+        let x = self
+            .horizontal_alignment
+            .offset(u32::from(area.width), u32::from(columns)) as u16
+            + area.left();
+        let y = self
+            .vertical_alignment
+            .offset(u32::from(area.height), u32::from(rows)) as u16
+            + area.top();
+
+        (image, Rect::new(x, y, columns, rows))
+    }
+
+    /// Fit the image to the pixels covered by `area` using the size of a
+    /// terminal cell, for drawing with a pixel protocol.
+    fn resize_to_pixels<'s>(
+        &self,
+        source: &dyn PixelView,
+        area: Rect,
+        state: &'s mut ImageState,
+    ) -> (&'s Bitmap, Rect) {
+        self.fit_to_cells(
+            source,
+            area,
+            u32::from(self.cell_size.width),
+            u32::from(self.cell_size.height),
+            1.0,
+            state,
+        )
+    }
+
+    /// Queue the image as a sixel sequence, reserving its cells in the
+    /// buffer.
+    fn render_sixel(
+        &self,
+        source: &dyn PixelView,
+        area: Rect,
+        buf: &mut Buffer,
+        matte: color::Rgb,
+        graphics: &Graphics,
+        state: &mut ImageState,
+    ) {
+        let (image, cells) = self.resize_to_pixels(source, area, state);
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let pixels = image
+            .pixels()
+            .iter()
+            .map(|pixel| color::blend(*pixel, matte))
+            .collect::<Vec<_>>();
+
+        // Sixel images are limited to a palette, so truecolor is reduced to
+        // the 256 color palette.
+        let depth = match self.color_depth {
+            ColorDepth::TrueColor => ColorDepth::Indexed256,
+            depth => depth,
+        };
+        let colors = self.dither.apply(depth, width, height, &pixels);
+
+        let pixels = image
+            .pixels()
+            .iter()
+            .zip(colors)
+            .map(|(pixel, color)| {
+                if self.overlay && pixel[3] == 0 {
+                    None
+                } else {
+                    color::to_rgb(color)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        self.reserve_cells(cells, buf);
+        graphics.queue(cells.x, cells.y, sixel::encode(width, height, &pixels));
+    }
+
+    /// Mark the cells covered by an image drawn with sixel or iTerm2 graphics.
+    ///
+    /// These images stay on screen until the text beneath them is redrawn,
+    /// but tui only redraws cells which changed since the last frame. Blank
+    /// cells under the image are drawn as hidden spaces, which look the same
+    /// but differ from plain blank cells, so the image is erased once it
+    /// stops covering them.
+    fn reserve_cells(&self, cells: Rect, buf: &mut Buffer) {
+        for y in cells.top()..cells.bottom() {
+            for x in cells.left()..cells.right() {
+                let cell = buf.get_mut(x, y);
+                // In overlay mode the text beneath the image is kept, since
+                // it shows through any transparent pixels.
+                if !self.overlay {
+                    cell.set_char(' ');
+                }
+                if cell.symbol == " " {
+                    cell.modifier.insert(Modifier::HIDDEN);
+                }
+            }
+        }
+    }
+
+    /// Convert the image to rgba, blending it against the matte color unless
+    /// drawing in overlay mode.
+    fn composite_rgba(&self, image: &Bitmap, matte: color::Rgb) -> Bitmap {
+        let mut image = image.clone();
+        if !self.overlay {
+            for pixel in image.pixels_mut() {
+                let [r, g, b] = color::blend(*pixel, matte);
+                *pixel = [r, g, b, u8::MAX];
+            }
+        }
+        image
+    }
+
+    /// Queue the image to be shown with the kitty graphics protocol, leaving
+    /// its cells in the buffer blank.
+    fn render_kitty(
+        &self,
+        source: &dyn PixelView,
+        area: Rect,
+        matte: color::Rgb,
+        graphics: &Graphics,
+        state: &mut ImageState,
+    ) {
+        let (image, cells) = self.resize_to_pixels(source, area, state);
+        let image = self.composite_rgba(image, matte);
+
+        graphics.queue_kitty(cells, image.width(), image.height(), image.as_bytes());
+    }
+
+    /// Queue the image to be shown with the iTerm2 inline image protocol,
+    /// reserving its cells in the buffer.
+    fn render_iterm2(
+        &self,
+        source: &dyn PixelView,
+        area: Rect,
+        buf: &mut Buffer,
+        matte: color::Rgb,
+        graphics: &Graphics,
+        state: &mut ImageState,
+    ) {
+        let (image, cells) = self.resize_to_pixels(source, area, state);
+        if image.width() == 0 || image.height() == 0 {
+            return;
         }
+        let Some(png) = iterm2::png(&self.composite_rgba(image, matte)) else {
+            return;
+        };
 
-        let image = self.image.resize(
-            u32::from(image_area.width),
-            u32::from(image_area.height),
-            self.filter_mode,
+        self.reserve_cells(cells, buf);
+        graphics.queue(
+            cells.x,
+            cells.y,
+            iterm2::encode(&png, cells.width, cells.height),
         );
+    }
+}
+
+/// Draw text centered in the area.
+fn render_centered(text: Text, area: Rect, buf: &mut Buffer) {
+    let height = (text.height() as u16).min(area.height);
+    let y_offset = Align::Center.offset(u32::from(area.height), u32::from(height)) as u16;
+
+    Paragraph::new(text).alignment(Alignment::Center).render(
+        Rect::new(area.x, area.y + y_offset, area.width, height),
+        buf,
+    );
+}
+
+/// Split text into lines of at most `width` characters, breaking between
+/// words where possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut line = String::new();
 
-        let x_start = (area.width - image.width() as u16) / 2 + image_area.left();
-        let y_start = (area.height - image.height() as u16 / 2) / 2 + image_area.top();
+    for mut word in text.split_whitespace() {
+        loop {
+            let (line_len, word_len) = (line.chars().count(), word.chars().count());
+            if line_len == 0 && word_len <= width {
+                line.push_str(word);
+                break;
+            } else if line_len > 0 && line_len + 1 + word_len <= width {
+                line.push(' ');
+                line.push_str(word);
+                break;
+            } else if line_len > 0 {
+                lines.push(mem::take(&mut line));
+            } else {
+                // The word doesn't fit on a line of its own, so it's split.
+                let split = word
+                    .char_indices()
+                    .nth(width)
+                    .map_or(word.len(), |(index, _)| index);
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            }
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Get the colors visible in the top & bottom halves of a cell.
+fn underlying_colors(cell: &Cell) -> (Color, Color) {
+    match cell.symbol.as_str() {
+        "▄" => (cell.bg, cell.fg),
+        "▀" => (cell.fg, cell.bg),
+        "█" => (cell.fg, cell.fg),
+        _ => (cell.bg, cell.bg),
+    }
+}
 
-        for (y_actual, y_pixel) in (0..image.height() as u16).step_by(2).enumerate() {
-            for x in 0..image.width() as u16 {
-                let cell = buf.get_mut(x_start + x, y_start + y_actual as u16);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                let bg_pixel = image.get_pixel(u32::from(x), u32::from(y_pixel));
-                cell.set_char(HALF_BLOCK)
-                    .set_bg(Color::Rgb(bg_pixel[0], bg_pixel[1], bg_pixel[2]));
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    /// Draw `image` into an empty `width` x `height` buffer.
+    fn render(image: Image, width: u16, height: u16) -> Buffer {
+        let area = Rect::new(0, 0, width, height);
+        let mut buffer = Buffer::empty(area);
+        Widget::render(image, area, &mut buffer);
+        buffer
+    }
 
-                if ((y_pixel + 1) as u32) < image.height() {
-                    let fg_pixel = image.get_pixel(u32::from(x), u32::from(y_pixel + 1));
-                    cell.set_fg(Color::Rgb(fg_pixel[0], fg_pixel[1], fg_pixel[2]));
+    /// Wrap rgba pixels in an image `width` pixels wide.
+    fn rgba(pixels: &[u8], width: u32) -> RawImage<'_> {
+        let height = (pixels.len() / 4) as u32 / width;
+        RawImage::new(pixels, width, height, width as usize * 4, PixelFormat::Rgba)
+    }
+
+    /// The (top, bottom) colors of a half block cell.
+    fn halves(buffer: &Buffer, x: u16, y: u16) -> (Color, Color) {
+        let cell = buffer.get(x, y);
+        assert_eq!(cell.symbol, "▄");
+        (cell.bg, cell.fg)
+    }
+
+    #[test]
+    fn blends_transparent_pixels_against_style_background() {
+        let pixels = [CLEAR, RED].concat();
+        let image = Image::new(rgba(&pixels, 1)).style(Style::default().bg(Color::Rgb(0, 0, 255)));
+        assert_eq!(
+            halves(&render(image, 1, 1), 0, 0),
+            (Color::Rgb(0, 0, 255), Color::Rgb(255, 0, 0))
+        );
+    }
+
+    #[test]
+    fn prefers_matte_over_style_background() {
+        let pixels = [CLEAR, [255, 255, 255, 128]].concat();
+        let image = Image::new(rgba(&pixels, 1))
+            .style(Style::default().bg(Color::Rgb(0, 0, 255)))
+            .matte(Color::Rgb(0, 255, 0));
+        assert_eq!(
+            halves(&render(image, 1, 1), 0, 0),
+            (Color::Rgb(0, 255, 0), Color::Rgb(128, 255, 128))
+        );
+    }
+
+    #[test]
+    fn blends_against_black_without_background() {
+        let pixels = [CLEAR, [255, 255, 255, 128]].concat();
+        assert_eq!(
+            halves(&render(Image::new(rgba(&pixels, 1)), 1, 1), 0, 0),
+            (Color::Rgb(0, 0, 0), Color::Rgb(128, 128, 128))
+        );
+    }
+
+    #[test]
+    fn overlays_existing_cells() {
+        let area = Rect::new(0, 0, 1, 2);
+        let mut buffer = Buffer::empty(area);
+        for y in 0..2 {
+            buffer
+                .get_mut(0, y)
+                .set_char('x')
+                .set_fg(Color::White)
+                .set_bg(Color::Rgb(0, 0, 255));
+        }
+
+        // The top cell is fully transparent, and the bottom cell is half
+        // transparent.
+        let pixels = [CLEAR, CLEAR, RED, CLEAR].concat();
+        Widget::render(
+            Image::new(rgba(&pixels, 1)).overlay(true),
+            area,
+            &mut buffer,
+        );
+
+        let top = buffer.get(0, 0);
+        assert_eq!(
+            (top.symbol.as_str(), top.fg, top.bg),
+            ("x", Color::White, Color::Rgb(0, 0, 255))
+        );
+        assert_eq!(
+            halves(&buffer, 0, 1),
+            (Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255))
+        );
+    }
+
+    #[test]
+    fn overlays_using_colors_of_half_blocks() {
+        let area = Rect::new(0, 0, 1, 1);
+        let mut buffer = Buffer::empty(area);
+        buffer
+            .get_mut(0, 0)
+            .set_char('▄')
+            .set_fg(Color::Rgb(0, 255, 0))
+            .set_bg(Color::Rgb(0, 0, 255));
+
+        let pixels = [[255, 0, 0, 128], CLEAR].concat();
+        Widget::render(
+            Image::new(rgba(&pixels, 1)).overlay(true),
+            area,
+            &mut buffer,
+        );
+        assert_eq!(
+            halves(&buffer, 0, 0),
+            (Color::Rgb(128, 0, 127), Color::Rgb(0, 255, 0))
+        );
+    }
+
+    #[test]
+    fn reserves_cells_drawn_with_sixel() {
+        let pixels = RED.repeat(20 * 40);
+        let graphics = Graphics::default();
+        let image = Image::new(rgba(&pixels, 20))
+            .protocol(Protocol::Sixel)
+            .graphics(&graphics);
+        let buffer = render(image, 4, 4);
+
+        // The 2x2 cells of the image are centered in the area.
+        for y in 0..4 {
+            for x in 0..4 {
+                let cell = buffer.get(x, y);
+                let covered = (1..3).contains(&x) && (1..3).contains(&y);
+                assert_eq!(cell.symbol, " ");
+                assert_eq!(
+                    cell.modifier.contains(Modifier::HIDDEN),
+                    covered,
+                    "({x}, {y})"
+                );
+            }
+        }
+
+        // Once the image is no longer drawn, its cells differ from the last
+        // frame so tui redraws them, erasing the image.
+        let empty = Buffer::empty(buffer.area);
+        assert_eq!(empty.diff(&buffer).len(), 4);
+    }
+
+    #[test]
+    fn skips_empty_images_with_iterm2() {
+        let graphics = Graphics::default();
+        for (width, height) in [(0, 0), (0, 4), (4, 0)] {
+            let image = Image::new(RawImage::new(
+                &[],
+                width,
+                height,
+                width as usize * 4,
+                PixelFormat::Rgba,
+            ))
+            .protocol(Protocol::Iterm2)
+            .graphics(&graphics);
+            let buffer = render(image, 4, 4);
+            assert_eq!(buffer, Buffer::empty(buffer.area));
+        }
+
+        let mut output = vec![];
+        graphics.flush(&mut output).unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn keeps_text_beneath_overlaid_sixels() {
+        let area = Rect::new(0, 0, 2, 2);
+        let mut buffer = Buffer::empty(area);
+        buffer.set_string(0, 0, "x", Style::default());
+
+        let pixels = RED.repeat(20 * 40);
+        let graphics = Graphics::default();
+        let image = Image::new(rgba(&pixels, 20))
+            .protocol(Protocol::Sixel)
+            .graphics(&graphics)
+            .overlay(true);
+        Widget::render(image, area, &mut buffer);
+
+        assert_eq!(buffer.get(0, 0).symbol, "x");
+        assert!(!buffer.get(0, 0).modifier.contains(Modifier::HIDDEN));
+        assert!(buffer.get(1, 1).modifier.contains(Modifier::HIDDEN));
+    }
+
+    #[test]
+    fn aligns_odd_height_images() {
+        const GREEN: [u8; 4] = [0, 255, 0, 255];
+        const BLUE: [u8; 4] = [0, 0, 255, 255];
+        let (red, green, blue, black) = (
+            Color::Rgb(255, 0, 0),
+            Color::Rgb(0, 255, 0),
+            Color::Rgb(0, 0, 255),
+            Color::Rgb(0, 0, 0),
+        );
+        let pixels = [RED, GREEN, BLUE].concat();
+
+        // The image covers two cells, leaving half of one of them for the
+        // matte color. It stays flush with the side it's aligned to.
+        let cases = [
+            (Align::Start, 0, [(red, green), (blue, black)]),
+            (Align::Center, 1, [(red, green), (blue, black)]),
+            (Align::End, 2, [(black, red), (green, blue)]),
+        ];
+        for (align, row, cells) in cases {
+            let image = Image::new(rgba(&pixels, 1)).vertical_alignment(align);
+            let buffer = render(image, 1, 4);
+            for y in 0..4 {
+                if (row..row + 2).contains(&y) {
+                    assert_eq!(
+                        halves(&buffer, 0, y),
+                        cells[usize::from(y - row)],
+                        "{align:?}"
+                    );
+                } else {
+                    assert_eq!(*buffer.get(0, y), Cell::default(), "{align:?}");
                 }
             }
         }
     }
+
+    #[test]
+    fn aligns_horizontally() {
+        let pixels = RED.repeat(2);
+        for (align, column) in [(Align::Start, 0), (Align::Center, 1), (Align::End, 2)] {
+            let image = Image::new(rgba(&pixels, 1)).horizontal_alignment(align);
+            let buffer = render(image, 3, 1);
+            for x in 0..3 {
+                assert_eq!(buffer.get(x, 0).symbol == "▄", x == column, "{align:?} {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn corrects_for_cell_aspect_ratio() {
+        /// The (columns, rows) of cells covered by the image.
+        fn covered(buffer: &Buffer) -> (usize, usize) {
+            let area = buffer.area;
+            let drawn = |x, y| buffer.get(x, y).symbol == "▄";
+            (
+                (0..area.width)
+                    .filter(|x| (0..area.height).any(|y| drawn(*x, y)))
+                    .count(),
+                (0..area.height)
+                    .filter(|y| (0..area.width).any(|x| drawn(x, *y)))
+                    .count(),
+            )
+        }
+
+        // Each half block pixel of a 10x22 cell is 1.1 times as tall as it is
+        // wide, so a square image is widened to match.
+        let pixels = RED.repeat(10 * 10);
+        let tall = CellSize {
+            width: 10,
+            height: 22,
+        };
+        let cases = [
+            (CellSize::default(), false, (10, 5)),
+            (tall, false, (11, 5)),
+            (tall, true, (20, 9)),
+        ];
+        for (cell_size, upscale, cells) in cases {
+            let image = Image::new(rgba(&pixels, 10))
+                .cell_size(cell_size)
+                .upscale(upscale);
+            assert_eq!(
+                covered(&render(image, 20, 20)),
+                cells,
+                "{cell_size:?} {upscale}"
+            );
+        }
+    }
+
+    #[cfg(all(unix, feature = "image"))]
+    #[test]
+    fn draws_load_errors_within_block() {
+        use tui::{
+            backend::TestBackend,
+            widgets::Borders,
+            Terminal,
+        };
+
+        let mut terminal = Terminal::new(TestBackend::new(24, 8)).unwrap();
+        terminal
+            .draw(|f| {
+                let image =
+                    Image::load("missing.png").block(Block::default().borders(Borders::ALL));
+                f.render_widget(image, f.size());
+            })
+            .unwrap();
+
+        let mut expected = Buffer::with_lines(vec![
+            "┌──────────────────────┐",
+            "│                      │",
+            "│           🖼          │",
+            "│      missing.png     │",
+            "│    No such file or   │",
+            "│directory (os error 2)│",
+            "│                      │",
+            "└──────────────────────┘",
+        ]);
+        expected.set_style(Rect::new(12, 2, 1, 1), Style::default().fg(Color::Red));
+        expected.set_style(
+            Rect::new(7, 3, 11, 1),
+            Style::default().add_modifier(Modifier::BOLD),
+        );
+        terminal.backend().assert_buffer(&expected);
+    }
 }
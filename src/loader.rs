@@ -0,0 +1,357 @@
+use std::{
+    io::{
+        self,
+        Cursor,
+    },
+    panic::{
+        self,
+        AssertUnwindSafe,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        mpsc::{
+            self,
+            Receiver,
+            Sender,
+        },
+        Arc,
+        Mutex,
+        OnceLock,
+    },
+    thread,
+};
+
+use image::{
+    io::Reader,
+    DynamicImage,
+    ImageError,
+    ImageResult,
+};
+
+use crate::Filter;
+
+/// Where a [`Loader`] reads an encoded image from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LoadSource {
+    /// Read the image from a file.
+    Path(PathBuf),
+    /// Decode the image from bytes in memory.
+    Bytes(Vec<u8>),
+}
+
+impl From<PathBuf> for LoadSource {
+    fn from(path: PathBuf) -> Self {
+        LoadSource::Path(path)
+    }
+}
+
+impl From<&Path> for LoadSource {
+    fn from(path: &Path) -> Self {
+        LoadSource::Path(path.to_path_buf())
+    }
+}
+
+impl From<Vec<u8>> for LoadSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        LoadSource::Bytes(bytes)
+    }
+}
+
+impl From<&str> for LoadSource {
+    fn from(path: &str) -> Self {
+        LoadSource::Path(path.into())
+    }
+}
+
+impl From<&[u8]> for LoadSource {
+    fn from(bytes: &[u8]) -> Self {
+        LoadSource::Bytes(bytes.to_vec())
+    }
+}
+
+impl LoadSource {
+    /// The file name of the image, if it is read from a file.
+    fn name(&self) -> Option<Arc<str>> {
+        match self {
+            LoadSource::Path(path) => Some(path.file_name()?.to_string_lossy().into()),
+            LoadSource::Bytes(_) => None,
+        }
+    }
+
+    /// Decode the image, detecting its format from its contents.
+    pub(crate) fn decode(&self) -> ImageResult<DynamicImage> {
+        match self {
+            LoadSource::Path(path) => Reader::open(path)?.with_guessed_format()?.decode(),
+            LoadSource::Bytes(bytes) => Reader::new(Cursor::new(bytes))
+                .with_guessed_format()?
+                .decode(),
+        }
+    }
+}
+
+/// Decodes & scales images on a pool of worker threads.
+///
+/// Decoding a large image and scaling it down can take far longer than a
+/// frame, so doing it while drawing freezes the UI. Each call to
+/// [`Loader::load`] instead queues the work & returns a [`LoadHandle`]
+/// immediately. Draw the handle with [`crate::Image::from_handle`], which
+/// shows a placeholder until the image is ready.
+///
+/// Images are scaled down to fit within the requested size, which should be
+/// at least the number of pixels the image will be drawn with, e.g. the size
+/// of its area in cells multiplied by the cell size. The final scaling is
+/// still done while drawing, but from a much smaller image.
+///
+/// The workers exit once the loader is dropped & any queued images have
+/// finished loading.
+///
+/// ```no_run
+/// # use std::{io, path::Path};
+/// # use tui::{backend::TestBackend, Terminal};
+/// # use tui_image::{Image, Loader};
+/// # let mut terminal = Terminal::new(TestBackend::new(80, 24))?;
+/// let loader = Loader::default();
+/// let handle = loader.load(Path::new("photo.jpg"), (1920, 1080));
+/// terminal.draw(|f| {
+///     let image = Image::from_handle(&handle).placeholder("Loading…");
+///     f.render_widget(image, f.size());
+/// })?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Loader {
+    jobs: Sender<Job>,
+    filter_mode: Filter,
+}
+
+/// An image queued to be loaded by the workers.
+struct Job {
+    source: LoadSource,
+    size: (u32, u32),
+    filter_mode: Filter,
+    result: Arc<OnceLock<ImageResult<DynamicImage>>>,
+}
+
+/// The pending result of an image queued with [`Loader::load`], or the
+/// result of an image loaded with [`LoadHandle::load`].
+///
+/// Handles are cheap to clone, and all clones share the same result. When
+/// drawn, a handle to an image which failed to load shows the error in place
+/// of the image.
+#[derive(Debug, Clone, Default)]
+pub struct LoadHandle {
+    result: Arc<OnceLock<ImageResult<DynamicImage>>>,
+    name: Option<Arc<str>>,
+}
+
+impl Default for Loader {
+    /// Create a loader with a worker for each available cpu.
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, |threads| threads.get()))
+    }
+}
+
+impl Loader {
+    /// Create a loader with `threads` workers, or one if `threads` is zero.
+    pub fn new(threads: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for _ in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || work(&receiver));
+        }
+
+        Self {
+            jobs,
+            filter_mode: Filter::Lanczos3,
+        }
+    }
+
+    /// Set the filter mode used to scale images down on the workers.
+    /// Defaults to [`Filter::Lanczos3`].
+    pub fn filter_mode(mut self, filter_mode: impl Into<Filter>) -> Self {
+        self.filter_mode = filter_mode.into();
+        self
+    }
+
+    /// Queue an image to be decoded & scaled down to fit within `size`
+    /// pixels. Images which already fit are left at their original size.
+    pub fn load(&self, source: impl Into<LoadSource>, size: (u32, u32)) -> LoadHandle {
+        let source = source.into();
+        let handle = LoadHandle::pending(&source);
+        let job = Job {
+            source,
+            size,
+            filter_mode: self.filter_mode,
+            result: Arc::clone(&handle.result),
+        };
+
+        // The workers only exit once the sender is dropped, so this can't fail
+        // while the loader is alive.
+        let _ = self.jobs.send(job);
+        handle
+    }
+}
+
+impl LoadHandle {
+    /// Decode an image on the current thread, detecting its format from its
+    /// contents.
+    ///
+    /// ```no_run
+    /// # use tui_image::LoadHandle;
+    /// let handle = LoadHandle::load("photo.jpg");
+    /// ```
+    pub fn load(source: impl Into<LoadSource>) -> Self {
+        let source = source.into();
+        let handle = Self::pending(&source);
+        handle.finish(source.decode());
+        handle
+    }
+
+    /// Create a handle for an image which hasn't loaded yet.
+    pub(crate) fn pending(source: &LoadSource) -> Self {
+        Self {
+            result: Arc::default(),
+            name: source.name(),
+        }
+    }
+
+    /// Set the result of loading the image, if it isn't already set.
+    pub(crate) fn finish(&self, result: ImageResult<DynamicImage>) {
+        let _ = self.result.set(result);
+    }
+
+    /// Whether the image has finished loading, successfully or not.
+    pub fn is_ready(&self) -> bool {
+        self.result.get().is_some()
+    }
+
+    /// Get the image, if it has loaded successfully.
+    pub fn image(&self) -> Option<&DynamicImage> {
+        self.result.get()?.as_ref().ok()
+    }
+
+    /// Get the error the image failed to load with, if any.
+    pub fn error(&self) -> Option<&ImageError> {
+        self.result.get()?.as_ref().err()
+    }
+
+    /// Get the file name of the image, if it is read from a file.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Run jobs from the queue until the loader is dropped.
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is only held while waiting for the next job, so the other
+        // workers can pick up jobs while this one is busy.
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else {
+            return;
+        };
+
+        // A panic while loading is stored as the result, rather than leaving
+        // the handle pending forever & taking the worker down with it.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            job.source
+                .decode()
+                .map(|image| shrink_to_fit(&image, job.size, job.filter_mode).unwrap_or(image))
+        }))
+        .unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            Err(ImageError::IoError(io::Error::other(format!(
+                "loading the image panicked: {message}"
+            ))))
+        });
+        let _ = job.result.set(result);
+    }
+}
+
+/// Scale an image down to fit within `size`, keeping its aspect ratio, or
+/// `None` if it already fits.
+pub(crate) fn shrink_to_fit(
+    image: &DynamicImage,
+    (width, height): (u32, u32),
+    filter_mode: Filter,
+) -> Option<DynamicImage> {
+    if image.width() <= width && image.height() <= height {
+        None
+    } else {
+        Some(image.resize(width.max(1), height.max(1), filter_mode.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        Instant,
+    };
+
+    use image::{
+        GenericImageView,
+        ImageOutputFormat,
+        RgbaImage,
+    };
+
+    use super::*;
+
+    /// Wait for a handle to finish loading.
+    fn wait(handle: &LoadHandle) {
+        let start = Instant::now();
+        while !handle.is_ready() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "timed out loading"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    #[test]
+    fn loads_and_shrinks_images() {
+        let loader = Loader::new(1);
+        let small = loader.load(png(4, 2), (16, 16));
+        let large = loader.load(png(64, 32), (16, 16));
+
+        wait(&small);
+        wait(&large);
+        assert_eq!(small.image().unwrap().dimensions(), (4, 2));
+        assert_eq!(large.image().unwrap().dimensions(), (16, 8));
+        assert_eq!(large.name(), None);
+    }
+
+    #[test]
+    fn keeps_loading_after_errors() {
+        let loader = Loader::new(1);
+        let broken = loader.load(&b"not an image"[..], (16, 16));
+        let image = loader.load(png(4, 2), (16, 16));
+
+        wait(&broken);
+        assert!(broken.error().is_some());
+        assert!(broken.image().is_none());
+        wait(&image);
+        assert!(image.image().is_some());
+    }
+}
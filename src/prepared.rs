@@ -0,0 +1,184 @@
+use tui::{
+    buffer::{
+        Buffer,
+        Cell,
+    },
+    layout::Rect,
+    style::{
+        Color,
+        Style,
+    },
+    widgets::Widget,
+};
+
+/// An image which has already been scaled & encoded into cells by
+/// [`crate::Image::prepare`].
+///
+/// Drawing a prepared image only copies its cells into the buffer, so the
+/// expensive work can be done ahead of time, e.g. on another thread, and the
+/// result drawn on every frame.
+///
+/// The cells are drawn from the top left of the area the image is rendered
+/// to, clipped to that area. Prepare the image again when the area changes
+/// size.
+///
+/// ```no_run
+/// # use std::{io, thread};
+/// # use tui::{backend::TestBackend, layout::Rect, Terminal};
+/// # use tui_image::{Image, PixelFormat, RawImage};
+/// # let mut terminal = Terminal::new(TestBackend::new(80, 24))?;
+/// # let image = RawImage::new(&[0; 64 * 64 * 4], 64, 64, 64 * 4, PixelFormat::Rgba);
+/// let area = Rect::new(0, 0, 80, 24);
+/// let prepared = thread::spawn(move || Image::new(&image).prepare(area))
+///     .join()
+///     .unwrap();
+/// terminal.draw(|f| f.render_widget(&prepared, f.size()))?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreparedImage {
+    buffer: Buffer,
+    /// Whether the symbol of each cell was written while preparing the image.
+    written: Vec<bool>,
+}
+
+/// The symbol cells are prepared with, telling which cells were written.
+const UNWRITTEN: &str = "\0";
+
+impl PreparedImage {
+    /// Create the buffer to prepare an image in.
+    pub(crate) fn buffer(area: Rect) -> Buffer {
+        let mut cell = Cell::default();
+        cell.set_symbol(UNWRITTEN);
+        Buffer::filled(area, &cell)
+    }
+
+    /// Finish preparing an image drawn into a buffer from
+    /// [`PreparedImage::buffer`].
+    pub(crate) fn new(mut buffer: Buffer) -> Self {
+        let written = buffer
+            .content
+            .iter_mut()
+            .map(|cell| {
+                let written = cell.symbol != UNWRITTEN;
+                if !written {
+                    cell.set_symbol(" ");
+                }
+                written
+            })
+            .collect();
+        Self { buffer, written }
+    }
+
+    /// The area the image was prepared for.
+    pub fn area(&self) -> Rect {
+        self.buffer.area
+    }
+}
+
+impl Widget for &PreparedImage {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let prepared = self.buffer.area;
+        let width = prepared.width.min(area.width);
+        let height = prepared.height.min(area.height);
+
+        for y in 0..height {
+            for x in 0..width {
+                // Only the symbols & colors the image wrote are copied,
+                // matching what drawing it directly would do.
+                let index = self.buffer.index_of(prepared.x + x, prepared.y + y);
+                let cell = &self.buffer.content[index];
+
+                let mut style = Style::default().add_modifier(cell.modifier);
+                if cell.fg != Color::Reset {
+                    style = style.fg(cell.fg);
+                }
+                if cell.bg != Color::Reset {
+                    style = style.bg(cell.bg);
+                }
+
+                let target = buf.get_mut(area.x + x, area.y + y);
+                if self.written[index] {
+                    target.set_symbol(&cell.symbol);
+                }
+                target.set_style(style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CellEncoding,
+        Image,
+        PixelFormat,
+        RawImage,
+    };
+
+    /// A buffer with text in every cell, for the image to be drawn over.
+    fn text(area: Rect) -> Buffer {
+        let mut buffer = Buffer::empty(area);
+        for y in area.top()..area.bottom() {
+            buffer.set_string(
+                area.x,
+                y,
+                "x".repeat(usize::from(area.width)),
+                Style::default(),
+            );
+        }
+        buffer
+    }
+
+    /// Check drawing a prepared image matches drawing it directly.
+    fn assert_matches_direct<'a>(image: impl Fn() -> Image<'a>, area: Rect) {
+        let mut direct = text(area);
+        image().render(area, &mut direct);
+
+        let prepared = image().prepare(area);
+        let mut blitted = text(area);
+        (&prepared).render(area, &mut blitted);
+
+        assert_eq!(blitted, direct);
+    }
+
+    #[test]
+    fn writes_blank_cells_like_direct_render() {
+        // The top cell has no dots, and is drawn as a space.
+        let mut pixels = [0, 0, 0, 255].repeat(2 * 4);
+        pixels.extend([255; 2 * 4 * 4]);
+
+        let encodings = [
+            CellEncoding::Braille { colored: false },
+            CellEncoding::Ascii {
+                ramp: CellEncoding::ASCII_RAMP,
+                colored: true,
+            },
+            CellEncoding::HalfBlock,
+        ];
+        for encoding in encodings {
+            for style in [Style::default(), Style::default().bg(Color::Blue)] {
+                assert_matches_direct(
+                    || {
+                        Image::new(RawImage::new(&pixels, 2, 8, 8, PixelFormat::Rgba))
+                            .cell_encoding(encoding.clone())
+                            .style(style)
+                    },
+                    Rect::new(0, 0, 3, 4),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn skips_transparent_cells_like_direct_render() {
+        let mut pixels = [0; 4 * 4];
+        pixels[8..].copy_from_slice(&[255, 0, 0, 255, 255, 0, 0, 255]);
+
+        assert_matches_direct(
+            || Image::new(RawImage::new(&pixels, 1, 4, 4, PixelFormat::Rgba)).overlay(true),
+            Rect::new(0, 0, 1, 2),
+        );
+    }
+}
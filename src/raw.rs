@@ -0,0 +1,308 @@
+use crate::{
+    ImageSource,
+    PixelView,
+};
+
+/// The layout of the pixels in a [`RawImage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// 3 bytes per pixel in red, green, blue order.
+    Rgb,
+    /// 4 bytes per pixel in red, green, blue, alpha order, with straight
+    /// alpha.
+    Rgba,
+    /// 4 bytes per pixel in blue, green, red, alpha order, with straight
+    /// alpha, as used by many windowing systems & capture apis.
+    Bgra,
+    /// Packed YUV 4:2:2, with 4 bytes in Y U Y V order for each pair of
+    /// pixels, as produced by many webcams.
+    Yuyv,
+    /// YUV 4:2:0 with a Y plane followed by a plane of interleaved U & V
+    /// samples at half resolution, as produced by many video decoders. Both
+    /// planes use the same stride.
+    Nv12,
+    /// YUV 4:2:0 with a Y plane followed by separate U & V planes at half
+    /// resolution, also known as I420. The U & V planes use half the stride,
+    /// rounded up.
+    Yuv420,
+}
+
+/// An image borrowed from a buffer of raw pixels, e.g. a video or camera
+/// frame, drawn without copying or converting it first.
+///
+/// Rows of pixels start `stride` bytes apart, which may be more than the
+/// size of a row when they are padded. YUV formats use the BT.601 limited
+/// range coefficients.
+///
+/// ```
+/// # use tui_image::{Image, PixelFormat, RawImage};
+/// let frame = vec![0; 640 * 480 * 4];
+/// let image = Image::new(RawImage::new(&frame, 640, 480, 640 * 4, PixelFormat::Bgra));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: PixelFormat,
+}
+
+impl<'a> RawImage<'a> {
+    /// Wrap a buffer holding a `width` x `height` image in `format`, with
+    /// rows starting `stride` bytes apart.
+    ///
+    /// # Panics
+    /// Panics if the stride is smaller than a row of pixels, or the buffer is
+    /// too small to hold the image.
+    pub fn new(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: PixelFormat,
+    ) -> Self {
+        let (columns, rows) = (width as usize, height as usize);
+        let row_len = match format {
+            PixelFormat::Rgb => columns * 3,
+            PixelFormat::Rgba | PixelFormat::Bgra => columns * 4,
+            PixelFormat::Yuyv => columns.div_ceil(2) * 4,
+            PixelFormat::Nv12 | PixelFormat::Yuv420 => columns,
+        };
+        assert!(stride >= row_len, "stride is smaller than a row of pixels");
+
+        let len = if columns == 0 || rows == 0 {
+            0
+        } else {
+            match format {
+                PixelFormat::Nv12 => {
+                    stride * rows + stride * (rows.div_ceil(2) - 1) + columns.div_ceil(2) * 2
+                }
+                PixelFormat::Yuv420 => {
+                    let chroma_stride = stride.div_ceil(2);
+                    stride * rows
+                        + chroma_stride * rows.div_ceil(2)
+                        + chroma_stride * (rows.div_ceil(2) - 1)
+                        + columns.div_ceil(2)
+                }
+                _ => stride * (rows - 1) + row_len,
+            }
+        };
+        assert!(data.len() >= len, "buffer is too small to hold the image");
+
+        Self {
+            data,
+            width,
+            height,
+            stride,
+            format,
+        }
+    }
+}
+
+impl PixelView for RawImage<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn address(&self) -> usize {
+        self.data.as_ptr() as usize
+    }
+
+    fn rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        let (x, y) = (x as usize, y as usize);
+        let data = self.data;
+        let row = y * self.stride;
+
+        match self.format {
+            PixelFormat::Rgb => {
+                let index = row + x * 3;
+                [data[index], data[index + 1], data[index + 2], u8::MAX]
+            }
+            PixelFormat::Rgba => {
+                let index = row + x * 4;
+                [
+                    data[index],
+                    data[index + 1],
+                    data[index + 2],
+                    data[index + 3],
+                ]
+            }
+            PixelFormat::Bgra => {
+                let index = row + x * 4;
+                [
+                    data[index + 2],
+                    data[index + 1],
+                    data[index],
+                    data[index + 3],
+                ]
+            }
+            PixelFormat::Yuyv => {
+                let index = row + x / 2 * 4;
+                yuv_to_rgba(data[index + x % 2 * 2], data[index + 1], data[index + 3])
+            }
+            PixelFormat::Nv12 => {
+                let chroma = self.stride * self.height as usize + y / 2 * self.stride + x / 2 * 2;
+                yuv_to_rgba(data[row + x], data[chroma], data[chroma + 1])
+            }
+            PixelFormat::Yuv420 => {
+                let chroma_stride = self.stride.div_ceil(2);
+                let u = self.stride * self.height as usize + y / 2 * chroma_stride + x / 2;
+                let v = u + chroma_stride * (self.height as usize).div_ceil(2);
+                yuv_to_rgba(data[row + x], data[u], data[v])
+            }
+        }
+    }
+}
+
+impl ImageSource for RawImage<'_> {
+    fn view(&self) -> Option<&dyn PixelView> {
+        Some(self)
+    }
+}
+
+/// Convert a limited range BT.601 YUV sample to opaque rgba.
+fn yuv_to_rgba(y: u8, u: u8, v: u8) -> [u8; 4] {
+    let c = 298 * (i32::from(y) - 16);
+    let d = i32::from(u) - 128;
+    let e = i32::from(v) - 128;
+
+    let channel = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    [
+        channel(c + 409 * e),
+        channel(c - 100 * d - 208 * e),
+        channel(c + 516 * d),
+        u8::MAX,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const GRAY: [u8; 4] = [76, 76, 76, 255];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+
+    /// The pixels of an image, row by row.
+    fn pixels(image: &RawImage) -> Vec<[u8; 4]> {
+        let (width, height) = image.dimensions();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| image.rgba(x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn converts_bt601_limited_range() {
+        assert_eq!(yuv_to_rgba(16, 128, 128), BLACK);
+        assert_eq!(yuv_to_rgba(235, 128, 128), WHITE);
+        assert_eq!(yuv_to_rgba(81, 128, 128), GRAY);
+        assert_eq!(yuv_to_rgba(81, 90, 240), RED);
+        // Values outside the limited range are clamped.
+        assert_eq!(yuv_to_rgba(0, 128, 128), BLACK);
+        assert_eq!(yuv_to_rgba(255, 128, 128), WHITE);
+    }
+
+    #[test]
+    fn skips_row_padding() {
+        #[rustfmt::skip]
+        let data = [
+            1, 2, 3, 4, 5, 6, 0, 0,
+            7, 8, 9, 10, 11, 12,
+        ];
+        let image = RawImage::new(&data, 2, 2, 8, PixelFormat::Rgb);
+        assert_eq!(
+            pixels(&image),
+            [
+                [1, 2, 3, 255],
+                [4, 5, 6, 255],
+                [7, 8, 9, 255],
+                [10, 11, 12, 255],
+            ]
+        );
+    }
+
+    #[test]
+    fn reorders_bgra() {
+        let data = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8];
+        let rgba = RawImage::new(&data, 1, 2, 8, PixelFormat::Rgba);
+        assert_eq!(pixels(&rgba), [[1, 2, 3, 4], [5, 6, 7, 8]]);
+        let bgra = RawImage::new(&data, 1, 2, 8, PixelFormat::Bgra);
+        assert_eq!(pixels(&bgra), [[3, 2, 1, 4], [7, 6, 5, 8]]);
+    }
+
+    #[test]
+    fn shares_yuyv_chroma_between_pixel_pairs() {
+        // An odd width still holds a whole pair of pixels for the last one.
+        let data = [16, 128, 235, 128, 81, 90, 0, 240];
+        let image = RawImage::new(&data, 3, 1, 8, PixelFormat::Yuyv);
+        assert_eq!(pixels(&image), [BLACK, WHITE, RED]);
+    }
+
+    #[test]
+    fn reads_interleaved_nv12_chroma() {
+        #[rustfmt::skip]
+        let data = [
+            // Y plane, with a byte of padding on each row.
+            16, 235, 0,
+            81, 81, 0,
+            81, 81, 0,
+            81, 81, 0,
+            // Interleaved U & V at half resolution, with the same stride.
+            128, 128, 0,
+            90, 240,
+        ];
+        let image = RawImage::new(&data, 2, 4, 3, PixelFormat::Nv12);
+        assert_eq!(
+            pixels(&image),
+            [BLACK, WHITE, GRAY, GRAY, RED, RED, RED, RED]
+        );
+    }
+
+    #[test]
+    fn reads_planar_yuv420_chroma() {
+        #[rustfmt::skip]
+        let data = [
+            // Y plane, with a byte of padding on each row.
+            16, 235, 0,
+            81, 81, 0,
+            81, 81, 0,
+            81, 81, 0,
+            // U then V planes at half resolution, with half the stride.
+            128, 0,
+            90, 0,
+            128, 0,
+            240,
+        ];
+        let image = RawImage::new(&data, 2, 4, 3, PixelFormat::Yuv420);
+        assert_eq!(
+            pixels(&image),
+            [BLACK, WHITE, GRAY, GRAY, RED, RED, RED, RED]
+        );
+    }
+
+    #[test]
+    fn accepts_empty_images() {
+        let image = RawImage::new(&[], 0, 0, 0, PixelFormat::Nv12);
+        assert_eq!(image.dimensions(), (0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "stride is smaller than a row of pixels")]
+    fn rejects_short_strides() {
+        RawImage::new(&[0; 16], 2, 2, 7, PixelFormat::Rgba);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer is too small to hold the image")]
+    fn rejects_short_buffers() {
+        RawImage::new(&[0; 15], 2, 2, 8, PixelFormat::Rgba);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer is too small to hold the image")]
+    fn rejects_buffers_missing_chroma() {
+        RawImage::new(&[0; 18], 2, 4, 3, PixelFormat::Yuv420);
+    }
+}
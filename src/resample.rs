@@ -0,0 +1,260 @@
+use std::f32::consts::PI;
+
+use crate::{
+    bitmap::Bitmap,
+    PixelView,
+};
+
+/// The filter used to resample the image when scaling it.
+///
+/// With the `image` feature, the filters of `image::imageops::FilterType`
+/// convert to their equivalent here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    /// Use the nearest pixel. Fast, but blocky when scaling up & prone to
+    /// aliasing when scaling down.
+    Nearest,
+    /// Linear interpolation.
+    Triangle,
+    /// Cubic interpolation using the Catmull-Rom spline.
+    CatmullRom,
+    /// A gaussian filter, which softens the image slightly.
+    Gaussian,
+    /// A windowed sinc filter over 3 pixels either side. The sharpest filter,
+    /// but also the slowest.
+    #[default]
+    Lanczos3,
+}
+
+#[cfg(feature = "image")]
+impl From<image::imageops::FilterType> for Filter {
+    fn from(filter: image::imageops::FilterType) -> Self {
+        use image::imageops::FilterType;
+
+        match filter {
+            FilterType::Nearest => Filter::Nearest,
+            FilterType::Triangle => Filter::Triangle,
+            FilterType::CatmullRom => Filter::CatmullRom,
+            FilterType::Gaussian => Filter::Gaussian,
+            FilterType::Lanczos3 => Filter::Lanczos3,
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<Filter> for image::imageops::FilterType {
+    fn from(filter: Filter) -> Self {
+        use image::imageops::FilterType;
+
+        match filter {
+            Filter::Nearest => FilterType::Nearest,
+            Filter::Triangle => FilterType::Triangle,
+            Filter::CatmullRom => FilterType::CatmullRom,
+            Filter::Gaussian => FilterType::Gaussian,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl Filter {
+    /// The distance from the center beyond which the kernel is zero, in
+    /// source pixels when scaling up.
+    fn support(self) -> f32 {
+        match self {
+            Filter::Nearest => 0.0,
+            Filter::Triangle => 1.0,
+            Filter::CatmullRom => 2.0,
+            Filter::Gaussian | Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// The unnormalized weight of a sample `x` pixels from the center.
+    fn kernel(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => 1.0,
+            Filter::Triangle => (1.0 - x).max(0.0),
+            Filter::CatmullRom => {
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Gaussian => (-2.0 * x * x).exp(),
+            Filter::Lanczos3 => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Calculate the source pixels & normalized weights contributing to each
+    /// of `output` pixels resampled from `input` pixels.
+    fn weights(self, input: u32, output: u32) -> Vec<(usize, Vec<f32>)> {
+        let ratio = input as f32 / output as f32;
+
+        (0..output)
+            .map(|index| {
+                let center = (index as f32 + 0.5) * ratio;
+                if self == Filter::Nearest {
+                    return ((center as usize).min(input as usize - 1), vec![1.0]);
+                }
+
+                // The kernel is widened when scaling down so every source
+                // pixel contributes to the output.
+                let scale = ratio.max(1.0);
+                let support = self.support() * scale;
+                let start = ((center - support).floor().max(0.0) as usize).min(input as usize - 1);
+                let end = ((center + support).ceil() as usize).clamp(start + 1, input as usize);
+
+                let mut weights = (start..end)
+                    .map(|source| self.kernel((source as f32 + 0.5 - center) / scale))
+                    .collect::<Vec<_>>();
+                let sum = weights.iter().sum::<f32>();
+                if sum != 0.0 {
+                    weights.iter_mut().for_each(|weight| *weight /= sum);
+                }
+                (start, weights)
+            })
+            .collect()
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * PI;
+        x.sin() / x
+    }
+}
+
+/// Scale `image` to exactly `width` x `height` pixels, resampling the rows
+/// then the columns.
+pub(crate) fn resize(image: &dyn PixelView, width: u32, height: u32, filter: Filter) -> Bitmap {
+    let (image_width, image_height) = image.dimensions();
+    if image_width == 0 || image_height == 0 || width == 0 || height == 0 {
+        return Bitmap::from_fn(width, height, |_, _| [0; 4]);
+    }
+
+    let columns = filter.weights(image_width, width);
+    let mut rows = Vec::with_capacity(width as usize * image_height as usize);
+    for y in 0..image_height {
+        for (start, weights) in &columns {
+            let mut sum = [0.0f32; 4];
+            for (offset, weight) in weights.iter().enumerate() {
+                let pixel = image.rgba((start + offset) as u32, y);
+                for channel in 0..4 {
+                    sum[channel] += f32::from(pixel[channel]) * weight;
+                }
+            }
+            rows.push(sum);
+        }
+    }
+
+    let weights = filter.weights(image_height, height);
+    let width = width as usize;
+    Bitmap::from_fn(width as u32, height, |x, y| {
+        let (start, weights) = &weights[y as usize];
+        let mut sum = [0.0f32; 4];
+        for (offset, weight) in weights.iter().enumerate() {
+            let pixel = rows[(start + offset) * width + x as usize];
+            for channel in 0..4 {
+                sum[channel] += pixel[channel] * weight;
+            }
+        }
+        sum.map(|channel| channel.round().clamp(0.0, 255.0) as u8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        PixelFormat,
+        RawImage,
+    };
+
+    const FILTERS: [Filter; 5] = [
+        Filter::Nearest,
+        Filter::Triangle,
+        Filter::CatmullRom,
+        Filter::Gaussian,
+        Filter::Lanczos3,
+    ];
+
+    fn rgba(pixels: &[[u8; 4]], width: u32) -> RawImage<'_> {
+        let height = pixels.len() as u32 / width;
+        RawImage::new(
+            pixels.as_flattened(),
+            width,
+            height,
+            width as usize * 4,
+            PixelFormat::Rgba,
+        )
+    }
+
+    #[test]
+    fn scales_to_requested_size() {
+        let pixels = [[0; 4]; 6];
+        for filter in FILTERS {
+            let image = resize(&rgba(&pixels, 3), 7, 5, filter);
+            assert_eq!((image.width(), image.height()), (7, 5), "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_solid_colors() {
+        const COLOR: [u8; 4] = [200, 100, 50, 128];
+        let small = [COLOR; 3 * 2];
+        let large = [COLOR; 7 * 5];
+        for filter in FILTERS {
+            let up = resize(&rgba(&small, 3), 7, 5, filter);
+            assert!(
+                up.pixels().iter().all(|&pixel| pixel == COLOR),
+                "{filter:?}"
+            );
+            let down = resize(&rgba(&large, 7), 3, 2, filter);
+            assert!(
+                down.pixels().iter().all(|&pixel| pixel == COLOR),
+                "{filter:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_pixels_at_the_same_size() {
+        let pixels = (0..12)
+            .map(|index| [index * 20, 255 - index * 20, index, 255])
+            .collect::<Vec<_>>();
+        // Every filter but the gaussian is zero at whole pixel offsets.
+        for filter in [
+            Filter::Nearest,
+            Filter::Triangle,
+            Filter::CatmullRom,
+            Filter::Lanczos3,
+        ] {
+            let image = resize(&rgba(&pixels, 4), 4, 3, filter);
+            assert_eq!(image.pixels(), pixels, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn averages_pixels_when_scaling_down() {
+        let pixels = [[0, 0, 0, 255], [255, 255, 255, 255]];
+        let image = resize(&rgba(&pixels, 2), 1, 1, Filter::Triangle);
+        assert_eq!(image.get(0, 0), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn clears_empty_images() {
+        let image = resize(&rgba(&[], 1), 2, 2, Filter::Lanczos3);
+        assert_eq!(image.pixels(), [[0; 4]; 4]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::Write;
+
+use crate::color::Rgb;
+
+/// Encode an image as a sixel escape sequence.
+///
+/// `pixels` holds the palette color of each pixel in row-major order, with
+/// `None` for pixels which should be left transparent.
+pub(crate) fn encode(width: usize, height: usize, pixels: &[Option<Rgb>]) -> String {
+    let mut palette: Vec<Rgb> = vec![];
+    let registers = pixels
+        .iter()
+        .map(|pixel| {
+            pixel.map(|rgb| match palette.iter().position(|entry| *entry == rgb) {
+                Some(register) => register,
+                None => {
+                    palette.push(rgb);
+                    palette.len() - 1
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // P2 = 1 leaves pixels with no color assigned unchanged.
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for (register, rgb) in palette.iter().enumerate() {
+        let [r, g, b] = rgb.map(|channel| (u32::from(channel) * 100 + 127) / 255);
+        let _ = write!(out, "#{register};2;{r};{g};{b}");
+    }
+
+    let mut sixels = vec![0u8; width];
+    for band in (0..height).step_by(6) {
+        let band_height = (height - band).min(6);
+
+        let mut used = vec![false; palette.len()];
+        for register in registers[band * width..(band + band_height) * width]
+            .iter()
+            .flatten()
+        {
+            used[*register] = true;
+        }
+
+        let mut first = true;
+        for register in (0..palette.len()).filter(|register| used[*register]) {
+            sixels.fill(0);
+            for row in 0..band_height {
+                let line = &registers[(band + row) * width..(band + row + 1) * width];
+                for (sixel, pixel) in sixels.iter_mut().zip(line) {
+                    if *pixel == Some(register) {
+                        *sixel |= 1 << row;
+                    }
+                }
+            }
+
+            if !first {
+                out.push('$');
+            }
+            first = false;
+
+            let _ = write!(out, "#{register}");
+            let end = sixels
+                .iter()
+                .rposition(|sixel| *sixel != 0)
+                .map_or(0, |last| last + 1);
+            push_runs(&mut out, &sixels[..end]);
+        }
+
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Write a line of sixels, run-length encoding repeats.
+fn push_runs(out: &mut String, sixels: &[u8]) {
+    let mut rest = sixels;
+    while let Some(sixel) = rest.first() {
+        let run = rest.iter().take_while(|next| *next == sixel).count();
+        let char = char::from(0x3f + sixel);
+        if run > 3 {
+            let _ = write!(out, "!{run}{char}");
+        } else {
+            out.extend(std::iter::repeat(char).take(run));
+        }
+        rest = &rest[run..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Option<Rgb> = Some([255, 0, 0]);
+    const BLUE: Option<Rgb> = Some([0, 0, 255]);
+
+    #[test]
+    fn defines_palette_registers() {
+        assert_eq!(
+            encode(2, 1, &[RED, BLUE]),
+            "\x1bP0;1;0q\"1;1;2;1#0;2;100;0;0#1;2;0;0;100#0@$#1?@-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn scales_channels_to_percentages() {
+        assert_eq!(
+            encode(1, 1, &[Some([128, 64, 1])]),
+            "\x1bP0;1;0q\"1;1;1;1#0;2;50;25;0#0@-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn run_length_encodes_repeats() {
+        assert_eq!(
+            encode(3, 1, &[RED; 3]),
+            "\x1bP0;1;0q\"1;1;3;1#0;2;100;0;0#0@@@-\x1b\\"
+        );
+        assert_eq!(
+            encode(5, 1, &[RED; 5]),
+            "\x1bP0;1;0q\"1;1;5;1#0;2;100;0;0#0!5@-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn splits_partial_bands() {
+        assert_eq!(
+            encode(1, 8, &[RED; 8]),
+            "\x1bP0;1;0q\"1;1;1;8#0;2;100;0;0#0~-#0B-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn only_selects_registers_used_in_band() {
+        let mut pixels = [RED; 7];
+        pixels[6] = BLUE;
+        assert_eq!(
+            encode(1, 7, &pixels),
+            "\x1bP0;1;0q\"1;1;1;7#0;2;100;0;0#1;2;0;0;100#0~-#1@-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn leaves_transparent_pixels_unset() {
+        assert_eq!(
+            encode(3, 1, &[None, RED, None]),
+            "\x1bP0;1;0q\"1;1;3;1#0;2;100;0;0#0?@-\x1b\\"
+        );
+        assert_eq!(encode(2, 1, &[None, None]), "\x1bP0;1;0q\"1;1;2;1-\x1b\\");
+    }
+}
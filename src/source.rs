@@ -0,0 +1,256 @@
+#[cfg(feature = "image")]
+use std::ops::Deref;
+use std::{
+    borrow::Cow,
+    error::Error,
+    rc::Rc,
+    sync::Arc,
+};
+
+#[cfg(feature = "image")]
+use image::{
+    DynamicImage,
+    GenericImageView,
+    ImageBuffer,
+    Pixel,
+    SubImage,
+};
+
+#[cfg(feature = "image")]
+use crate::LoadHandle;
+
+/// Something which can be drawn by an [`crate::Image`].
+///
+/// This is implemented for [`crate::RawImage`]s and, with the `image`
+/// feature, for `DynamicImage`s, `ImageBuffer`s with 8-bit channels such as
+/// `RgbImage` & `RgbaImage`, and `SubImage` views of them. Any other
+/// `GenericImageView` with 8-bit channels can be drawn by wrapping it in a
+/// `View`. Each of these can be borrowed, owned, boxed or shared. Pixels are
+/// read directly from the source, so it doesn't have to be converted to a
+/// `DynamicImage` first.
+pub trait ImageSource {
+    /// Get the pixels to draw, or `None` if there is nothing to draw yet.
+    fn view(&self) -> Option<&dyn PixelView>;
+
+    /// Get the error the image failed to load with, if any. It is shown in
+    /// place of the image.
+    fn error(&self) -> Option<&dyn Error> {
+        None
+    }
+
+    /// Get a name for the image, e.g. its file name, shown along with any
+    /// error.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Read access to the pixels of an image as 8-bit rgba.
+///
+/// With the `image` feature, this is implemented for every
+/// `GenericImageView` with 8-bit channels.
+pub trait PixelView {
+    /// The width & height of the image in pixels.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Get the straight alpha rgba value of the pixel at (`x`, `y`).
+    fn rgba(&self, x: u32, y: u32) -> [u8; 4];
+
+    /// The address of the pixels, which [`crate::ImageState`] uses to tell
+    /// when a different image is drawn. Defaults to the address of the view
+    /// itself.
+    fn address(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+#[cfg(feature = "image")]
+impl<I> PixelView for I
+where
+    I: GenericImageView,
+    I::Pixel: Pixel<Subpixel = u8>,
+{
+    fn dimensions(&self) -> (u32, u32) {
+        GenericImageView::dimensions(self)
+    }
+
+    fn rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        self.get_pixel(x, y).to_rgba().0
+    }
+}
+
+#[cfg(feature = "image")]
+impl ImageSource for DynamicImage {
+    fn view(&self) -> Option<&dyn PixelView> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "image")]
+impl<P, C> ImageSource for ImageBuffer<P, C>
+where
+    P: Pixel<Subpixel = u8>,
+    C: Deref<Target = [u8]>,
+{
+    fn view(&self) -> Option<&dyn PixelView> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "image")]
+impl<I> ImageSource for SubImage<I>
+where
+    I: Deref,
+    I::Target: GenericImageView,
+    <I::Target as GenericImageView>::Pixel: Pixel<Subpixel = u8>,
+{
+    fn view(&self) -> Option<&dyn PixelView> {
+        Some(&**self)
+    }
+}
+
+/// Draws any `GenericImageView` with 8-bit channels, such as
+/// `image::flat::View` or a view implemented by the application.
+///
+/// Requires the `image` feature.
+///
+/// ```
+/// # use image::{Rgba, RgbaImage};
+/// # use tui_image::{Image, View};
+/// let buffer = RgbaImage::new(64, 64);
+/// let samples = buffer.as_flat_samples();
+/// let image = Image::new(View(samples.as_view::<Rgba<u8>>().unwrap()));
+/// ```
+#[cfg(feature = "image")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct View<I>(pub I);
+
+#[cfg(feature = "image")]
+impl<I> ImageSource for View<I>
+where
+    I: GenericImageView,
+    I::Pixel: Pixel<Subpixel = u8>,
+{
+    fn view(&self) -> Option<&dyn PixelView> {
+        Some(&self.0)
+    }
+}
+
+#[cfg(feature = "image")]
+impl ImageSource for LoadHandle {
+    fn view(&self) -> Option<&dyn PixelView> {
+        self.image().map(|image| image as &dyn PixelView)
+    }
+
+    fn error(&self) -> Option<&dyn Error> {
+        LoadHandle::error(self).map(|error| error as &dyn Error)
+    }
+
+    fn name(&self) -> Option<&str> {
+        LoadHandle::name(self)
+    }
+}
+
+impl<T: ImageSource + ?Sized> ImageSource for &T {
+    fn view(&self) -> Option<&dyn PixelView> {
+        (**self).view()
+    }
+
+    fn error(&self) -> Option<&dyn Error> {
+        (**self).error()
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+}
+
+impl<T: ImageSource + ?Sized> ImageSource for Box<T> {
+    fn view(&self) -> Option<&dyn PixelView> {
+        (**self).view()
+    }
+
+    fn error(&self) -> Option<&dyn Error> {
+        (**self).error()
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+}
+
+impl<T: ImageSource + ?Sized> ImageSource for Rc<T> {
+    fn view(&self) -> Option<&dyn PixelView> {
+        (**self).view()
+    }
+
+    fn error(&self) -> Option<&dyn Error> {
+        (**self).error()
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+}
+
+impl<T: ImageSource + ?Sized> ImageSource for Arc<T> {
+    fn view(&self) -> Option<&dyn PixelView> {
+        (**self).view()
+    }
+
+    fn error(&self) -> Option<&dyn Error> {
+        (**self).error()
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+}
+
+impl<T: ImageSource + ToOwned + ?Sized> ImageSource for Cow<'_, T> {
+    fn view(&self) -> Option<&dyn PixelView> {
+        (**self).view()
+    }
+
+    fn error(&self) -> Option<&dyn Error> {
+        (**self).error()
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+}
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// A view generating its pixels rather than storing them.
+    struct Gradient;
+
+    impl GenericImageView for Gradient {
+        type Pixel = Rgba<u8>;
+
+        fn dimensions(&self) -> (u32, u32) {
+            (4, 2)
+        }
+
+        fn bounds(&self) -> (u32, u32, u32, u32) {
+            (0, 0, 4, 2)
+        }
+
+        fn get_pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+            Rgba([x as u8, y as u8, 0, 255])
+        }
+    }
+
+    #[test]
+    fn draws_custom_views() {
+        let source = View(Gradient);
+        let view = source.view().unwrap();
+        assert_eq!(PixelView::dimensions(view), (4, 2));
+        assert_eq!(view.rgba(3, 1), [3, 1, 0, 255]);
+    }
+}
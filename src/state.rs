@@ -0,0 +1,136 @@
+use crate::{
+    bitmap::Bitmap,
+    Align,
+    Filter,
+    Fit,
+};
+
+/// The state of an [`crate::Image`] drawn as a
+/// [`tui::widgets::StatefulWidget`].
+///
+/// Scaling the image is by far the most expensive part of drawing it, so the
+/// state keeps the scaled image between frames and only scales it again when
+/// the area, the scaling options or the source image change.
+///
+/// The source image is identified by where its pixels are stored, see
+/// [`crate::PixelView::address`], along with its dimensions & the version set
+/// with [`crate::Image::version`]. Bump the version when the image is
+/// modified in place, or when drawing a different image which may have been
+/// stored in the memory of the previous one, e.g. an owned image created
+/// anew for every frame. [`ImageState::clear`] also forces the image to be
+/// scaled again.
+///
+/// ```no_run
+/// # use std::io;
+/// # use tui::{backend::TestBackend, Terminal};
+/// # use tui_image::{Image, ImageState, PixelFormat, RawImage};
+/// # let mut terminal = Terminal::new(TestBackend::new(80, 24))?;
+/// # let image = RawImage::new(&[0; 64 * 64 * 4], 64, 64, 64 * 4, PixelFormat::Rgba);
+/// let mut state = ImageState::default();
+/// terminal.draw(|f| {
+///     f.render_stateful_widget(Image::new(&image), f.size(), &mut state);
+/// })?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ImageState {
+    cache: Option<(ResizeKey, Bitmap)>,
+}
+
+/// The inputs which determine the result of scaling an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ResizeKey {
+    pub(crate) source: usize,
+    pub(crate) source_dimensions: (u32, u32),
+    pub(crate) version: u64,
+    pub(crate) target: (u32, u32),
+    pub(crate) stretch: f64,
+    pub(crate) fit: Fit,
+    pub(crate) alignment: (Align, Align),
+    pub(crate) upscale: bool,
+    pub(crate) filter: Filter,
+}
+
+impl ImageState {
+    /// Drop the cached image, forcing it to be scaled again on the next draw.
+    pub fn clear(&mut self) {
+        self.cache = None;
+    }
+
+    /// Get the cached image for `key`, replacing the cache with the result of
+    /// `resize` if it was scaled with different inputs.
+    pub(crate) fn resized(&mut self, key: ResizeKey, resize: impl FnOnce() -> Bitmap) -> &Bitmap {
+        if self
+            .cache
+            .as_ref()
+            .is_some_and(|(cached, _)| *cached != key)
+        {
+            self.cache = None;
+        }
+        &self.cache.get_or_insert_with(|| (key, resize())).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::Color,
+        widgets::StatefulWidget,
+    };
+
+    use super::*;
+    use crate::{
+        Image,
+        PixelFormat,
+        RawImage,
+    };
+
+    /// Draw a 1x2 rgba image into a single half block cell, returning its
+    /// colors.
+    fn draw(pixels: &[u8], version: u64, state: &mut ImageState) -> (Color, Color) {
+        let area = Rect::new(0, 0, 1, 1);
+        let mut buffer = Buffer::empty(area);
+        let image = RawImage::new(pixels, 1, 2, 4, PixelFormat::Rgba);
+        StatefulWidget::render(Image::new(image).version(version), area, &mut buffer, state);
+
+        let cell = buffer.get(0, 0);
+        (cell.fg, cell.bg)
+    }
+
+    #[test]
+    fn reuses_scaled_image_until_version_changes() {
+        let red = (Color::Rgb(255, 0, 0), Color::Rgb(255, 0, 0));
+        let green = (Color::Rgb(0, 255, 0), Color::Rgb(0, 255, 0));
+
+        let mut state = ImageState::default();
+        let mut pixels = [255, 0, 0, 255].repeat(2);
+        assert_eq!(draw(&pixels, 0, &mut state), red);
+
+        // Modifying the image in place isn't noticed until its version is
+        // bumped.
+        pixels.copy_from_slice(&[0, 255, 0, 255].repeat(2));
+        assert_eq!(draw(&pixels, 0, &mut state), red);
+        assert_eq!(draw(&pixels, 1, &mut state), green);
+
+        pixels.copy_from_slice(&[255, 0, 0, 255].repeat(2));
+        state.clear();
+        assert_eq!(draw(&pixels, 1, &mut state), red);
+    }
+
+    #[test]
+    fn scales_again_for_different_images() {
+        let mut state = ImageState::default();
+        let red = [255, 0, 0, 255].repeat(2);
+        let green = [0, 255, 0, 255].repeat(2);
+        assert_eq!(
+            draw(&red, 0, &mut state),
+            (Color::Rgb(255, 0, 0), Color::Rgb(255, 0, 0))
+        );
+        assert_eq!(
+            draw(&green, 0, &mut state),
+            (Color::Rgb(0, 255, 0), Color::Rgb(0, 255, 0))
+        );
+    }
+}